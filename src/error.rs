@@ -2,15 +2,68 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use rustyline::error::ReadlineError;
+use script::Exception;
 use std::fmt::{self, Display, Formatter};
 use std::io;
-use std::string::FromUtf8Error;
 
 pub enum Error {
     Error,
-    InvalidString(FromUtf8Error),
+    /// The script could not be compiled.
+    Syntax(Exception),
+    /// The script threw an exception that it did not catch.
+    Runtime(Exception),
+    /// A script could not be decoded.
+    InvalidString(String),
     IO(io::Error),
-    MissingArgument,
+    InvalidArgument(String),
+    /// Running the named script failed.
+    InScript(String, Box<Error>),
+    /// Reading a line of input in the REPL failed.
+    Readline(ReadlineError),
+    /// The script ran for longer than the timeout allowed.
+    Timeout,
+    /// The script called `process.exit()` with the given code.
+    Exit(i32),
+}
+
+impl Error {
+    /// The process exit code for this error:
+    ///
+    /// * 1: any other error;
+    /// * 2: the command line was invalid;
+    /// * 3: a script could not be compiled;
+    /// * 4: a script threw an exception that it did not catch;
+    /// * 5: reading a script or other input failed;
+    /// * 6: a script could not be decoded;
+    /// * 7: a script ran for longer than the timeout allowed.
+    ///
+    /// A script that calls `process.exit()` chooses its own code.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Error => 1,
+            Error::InvalidArgument(_) => 2,
+            Error::Syntax(_) => 3,
+            Error::Runtime(_) => 4,
+            Error::IO(_) | Error::Readline(_) => 5,
+            Error::InvalidString(_) => 6,
+            Error::Timeout => 7,
+            Error::Exit(code) => code,
+            Error::InScript(_, ref error) => error.exit_code(),
+        }
+    }
+
+    /// A multi-line description of the error, beyond its `Display` form, if
+    /// there is one.
+    pub fn details(&self) -> Option<String> {
+        match *self {
+            Error::Syntax(ref exception) | Error::Runtime(ref exception) => {
+                Some(exception.details())
+            }
+            Error::InScript(_, ref error) => error.details(),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Error {
@@ -19,35 +72,47 @@ impl Display for Error {
             Error::Error => {
                 write!(formatter, "an unspecified error occurred")
             }
+            Error::Syntax(ref exception) => {
+                write!(formatter, "a syntax error was found: {}", exception)
+            }
+            Error::Runtime(ref exception) => {
+                write!(formatter, "an uncaught exception was thrown: {}", exception)
+            }
             Error::InvalidString(ref error) => {
                 write!(formatter,
-                       "an error occurred decoding a string ({:?})",
+                       "an error occurred decoding a string ({})",
                        error)
             }
             Error::IO(ref error) => {
                 write!(formatter, "an input/output error occurred ({:?})", error)
             }
-            Error::MissingArgument => {
-                write!(formatter, "a required argument was omitted")
+            Error::InvalidArgument(ref argument) => {
+                write!(formatter, "an invalid argument was given ({})", argument)
+            }
+            Error::InScript(ref filename, ref error) => {
+                write!(formatter, "{} (while running {})", error, filename)
+            }
+            Error::Readline(ref error) => {
+                write!(formatter, "an error occurred reading input ({:?})", error)
+            }
+            Error::Timeout => {
+                write!(formatter, "the script timed out")
+            }
+            Error::Exit(code) => {
+                write!(formatter, "the script exited with code {}", code)
             }
         }
     }
 }
 
-impl From<()> for Error {
-    fn from(_: ()) -> Error {
-        Error::Error
-    }
-}
-
-impl From<FromUtf8Error> for Error {
-    fn from(e: FromUtf8Error) -> Error {
-        Error::InvalidString(e)
-    }
-}
-
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
         Error::IO(e)
     }
 }
+
+impl From<ReadlineError> for Error {
+    fn from(e: ReadlineError) -> Error {
+        Error::Readline(e)
+    }
+}
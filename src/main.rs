@@ -5,52 +5,436 @@
 #![feature(cell_extras)]
 #![feature(plugin)]
 #![feature(plugin_registrar)]
+#![feature(recover)]
 #![feature(rustc_private)]
+#![feature(str_utf16)]
 
 #![plugin(clippy)]
 
 extern crate clippy;
+extern crate encoding;
 extern crate env_logger;
+extern crate getopts;
 extern crate js;
 extern crate libc;
 extern crate rustc_plugin;
+extern crate rustc_serialize;
+extern crate rustyline;
+extern crate time;
 
 mod error;
+mod repl;
 mod script;
 
+use encoding::EncodingRef;
+use encoding::label::encoding_from_whatwg_label;
 use error::Error;
 use rustc_plugin::Registry;
+use script::{ColorChoice, ConsoleMessageHandler, JsonHandler, Source, TerminalHandler};
 use std::env;
 use std::ffi::OsString;
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process;
+use std::rc::Rc;
+use std::time::Duration;
 
 #[plugin_registrar]
 pub fn plugin_registrar(registry: &mut Registry) {
     clippy::plugin_registrar(registry);
 }
 
-fn do_main(path: Option<OsString>) -> Result<(), Error> {
-    let path = try!(path.ok_or(Error::MissingArgument));
-    script::run_script(Path::new(&path))
+/// Where console messages are written.
+enum ConsoleOutput {
+    /// Text on stdout and stderr, coloured as chosen.
+    Text(ColorChoice),
+    /// JSON Lines, on stdout or to the given file.
+    Json(Option<PathBuf>),
+}
+
+impl ConsoleOutput {
+    fn handler(&self) -> Result<Rc<ConsoleMessageHandler>, Error> {
+        Ok(match *self {
+            ConsoleOutput::Text(color) => Rc::new(TerminalHandler::new(color)),
+            ConsoleOutput::Json(None) => Rc::new(JsonHandler::new(Box::new(io::stdout()))),
+            ConsoleOutput::Json(Some(ref path)) => {
+                Rc::new(JsonHandler::new(Box::new(try!(File::create(path)))))
+            }
+        })
+    }
+}
+
+/// What to run.
+enum Input {
+    /// The script at the given path, or on stdin.
+    Script(Source),
+    /// Code given on the command line, whose completion value is printed
+    /// if `print` is set.
+    Code {
+        code: String,
+        print: bool,
+    },
+    /// The interactive REPL.
+    Repl,
+    /// Only the scripts given with `-f`.
+    Nothing,
+    /// Nothing at all; print the usage instead.
+    Help,
+}
+
+struct Options {
+    /// The scripts given with `-f`, run in order before the input.
+    scripts: Vec<Source>,
+    input: Input,
+    output: ConsoleOutput,
+    /// The arguments after the script path, or after the options when
+    /// there is none, exposed to JavaScript as `scriptArgs`.
+    script_args: Vec<String>,
+    /// How long the scripts may run for.
+    timeout: Option<Duration>,
+    /// The encoding of scripts without a byte order mark.
+    encoding: Option<EncodingRef>,
+}
+
+/// The name under which code given with `--eval` or `--print` is compiled.
+const EVAL_FILENAME: &'static str = "<eval>";
+
+/// The first lines of the usage message.
+const USAGE: &'static str = "Usage: runtime [OPTIONS] [SCRIPT | -] [ARGUMENTS...]
+
+Run SCRIPT, or the script on stdin with -, passing it the ARGUMENTS as
+scriptArgs. The files given with -f run first, in the same global. Without
+a script, -e, -p or -f, start an interactive REPL.";
+
+/// The end of the usage message, matching `Error::exit_code()`.
+const EXIT_CODES: &'static str = "Exit codes:
+    0   success, unless the script set process.exitCode
+    1   any other error
+    2   the command line was invalid
+    3   a script could not be compiled
+    4   a script threw an exception that it did not catch
+    5   reading a script or other input failed
+    6   a script could not be decoded
+    7   a script ran for longer than the timeout allowed
+A script that calls process.exit() chooses its own code.";
+
+/// The command-line options.
+fn parser() -> getopts::Options {
+    let mut parser = getopts::Options::new();
+    // Everything after the script path belongs to the script.
+    parser.parsing_style(getopts::ParsingStyle::StopAtFirstFree);
+    parser.optflag("h", "help", "print this message");
+    parser.optmulti("f", "file", "run FILE before the script; may be repeated", "FILE");
+    parser.optopt("e", "eval", "evaluate CODE instead of a script", "CODE");
+    parser.optopt("p", "print", "evaluate CODE and print its completion value", "CODE");
+    parser.optflag("", "console-json", "write console messages to stdout as JSON Lines");
+    parser.optopt("",
+                  "console-json-file",
+                  "write console messages to FILE as JSON Lines",
+                  "FILE");
+    parser.optopt("", "color", "colour console output: auto, always or never", "WHEN");
+    parser.optopt("", "timeout", "stop the scripts after SECONDS", "SECONDS");
+    parser.optopt("",
+                  "encoding",
+                  "decode scripts without a byte order mark as LABEL, e.g., windows-1252",
+                  "LABEL");
+    parser
+}
+
+/// The usage message: the options and the exit codes.
+fn usage() -> String {
+    format!("{}\n{}", parser().usage(USAGE), EXIT_CODES)
+}
+
+/// Parse the command line.
+///
+/// Only the first path that is not an option is run as a script; any that
+/// follow it are arguments for the script, so `runtime a.js b.js` passes
+/// `b.js` to `a.js` as `scriptArgs`. To run several scripts, one after the
+/// other in the same global, give all but the last with `-f`, e.g.,
+/// `runtime -f a.js b.js`, or all of them, e.g., `runtime -f a.js -f b.js`.
+fn parse_args<I>(args: I) -> Result<Options, Error>
+    where I: IntoIterator<Item = OsString>
+{
+    // getopts only deals in UTF-8; keep the original arguments, so the free
+    // ones, which come last, can be recovered intact.
+    let args = args.into_iter().collect::<Vec<_>>();
+    let lossy_args = args.iter().map(|arg| arg.to_string_lossy().into_owned());
+    let matches = try!(parser().parse(lossy_args)
+                             .map_err(|fail| Error::InvalidArgument(fail.to_string())));
+    let mut free = args[args.len() - matches.free.len()..].iter();
+
+    let output = if let Some(path) = matches.opt_str("console-json-file") {
+        ConsoleOutput::Json(Some(PathBuf::from(path)))
+    } else if matches.opt_present("console-json") {
+        ConsoleOutput::Json(None)
+    } else {
+        let color = match matches.opt_str("color") {
+            Some(name) => {
+                match ColorChoice::from_name(&name) {
+                    Some(color) => color,
+                    None => return Err(Error::InvalidArgument(format!("--color={}", name))),
+                }
+            }
+            None => ColorChoice::Auto,
+        };
+        ConsoleOutput::Text(color)
+    };
+
+    let timeout = match matches.opt_str("timeout") {
+        Some(seconds) => {
+            match seconds.parse() {
+                Ok(seconds) => Some(Duration::from_secs(seconds)),
+                Err(_) => return Err(Error::InvalidArgument(format!("--timeout={}", seconds))),
+            }
+        }
+        None => None,
+    };
+
+    let encoding = match matches.opt_str("encoding") {
+        Some(label) => {
+            match encoding_from_whatwg_label(&label) {
+                Some(encoding) => Some(encoding),
+                None => return Err(Error::InvalidArgument(format!("--encoding={}", label))),
+            }
+        }
+        None => None,
+    };
+
+    if matches.opt_present("eval") && matches.opt_present("print") {
+        return Err(Error::InvalidArgument("-e together with -p".to_owned()));
+    }
+
+    let scripts = matches.opt_strs("file")
+                             .iter()
+                             .map(|arg| Source::from_arg(arg.as_ref()))
+                             .collect::<Vec<_>>();
+    let input = if matches.opt_present("help") {
+        Input::Help
+    } else if let Some(code) = matches.opt_str("print") {
+        Input::Code {
+            code: code,
+            print: true,
+        }
+    } else if let Some(code) = matches.opt_str("eval") {
+        Input::Code {
+            code: code,
+            print: false,
+        }
+    } else if let Some(arg) = free.next() {
+        Input::Script(Source::from_arg(arg))
+    } else if !scripts.is_empty() {
+        Input::Nothing
+    } else {
+        Input::Repl
+    };
+    // Standard input can only be read once.
+    let mut stdin_sources = scripts.iter().filter(|source| **source == Source::Stdin).count();
+    if let Input::Script(Source::Stdin) = input {
+        stdin_sources += 1;
+    }
+    if stdin_sources > 1 {
+        return Err(Error::InvalidArgument("- given more than once".to_owned()));
+    }
+    // Arguments that are not valid Unicode get replacement characters.
+    let script_args = free.map(|arg| arg.to_string_lossy().into_owned()).collect();
+
+    Ok(Options {
+        scripts: scripts,
+        input: input,
+        output: output,
+        script_args: script_args,
+        timeout: timeout,
+        encoding: encoding,
+    })
+}
+
+/// Run what the command line asks for, returning the exit code.
+fn do_main(options: Options) -> Result<i32, Error> {
+    if let Input::Help = options.input {
+        println!("{}", usage());
+        return Ok(0);
+    }
+    let console_handler = try!(options.output.handler());
+    let scripts = options.scripts;
+    let input = options.input;
+    let timeout = options.timeout;
+    let encoding = options.encoding;
+    script::with_context(console_handler, options.script_args, |context| {
+        if let Some(encoding) = encoding {
+            context.set_encoding(encoding);
+        }
+        // The REPL waits on the user, so it is not timed.
+        let _watchdog = match input {
+            Input::Repl => None,
+            _ => timeout.map(|timeout| context.watchdog(timeout)),
+        };
+        try!(context.run_scripts(&scripts));
+        try!(match input {
+            Input::Script(source) => context.run_scripts(&[source]),
+            Input::Code { code, print: true } => {
+                println!("{}", try!(context.evaluate_and_inspect(&code, EVAL_FILENAME, 1)));
+                Ok(())
+            }
+            Input::Code { code, print: false } => context.evaluate(&code, EVAL_FILENAME, 1),
+            Input::Repl => repl::run(context),
+            Input::Nothing | Input::Help => Ok(()),
+        });
+        context.exit_code()
+    })
 }
 
 fn main() {
     env_logger::init().unwrap();
-    match do_main(env::args_os().nth(1)) {
-        Ok(()) => println!("Hello, world!"),
+    let code = match parse_args(env::args_os().skip(1)).and_then(do_main) {
+        Ok(code) | Err(Error::Exit(code)) => code,
         Err(error) => {
-            println!("Finished unsuccessfully: {}.", error);
-            process::exit(1);
+            let mut stderr = io::stderr();
+            let _ = writeln!(stderr, "Finished unsuccessfully: {}.", error);
+            if let Some(details) = error.details() {
+                let _ = writeln!(stderr, "{}", details);
+            }
+            if let Error::InvalidArgument(_) = error {
+                let _ = writeln!(stderr, "\n{}", usage());
+            }
+            error.exit_code()
+        }
+    };
+    process::exit(code);
+}
+
+#[test]
+fn no_arguments() {
+    match parse_args(std::iter::empty()) {
+        Ok(Options { input: Input::Repl, .. }) => (),
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn help() {
+    match parse_args(vec![OsString::from("--help")]) {
+        Ok(Options { input: Input::Help, .. }) => (),
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+    assert!(usage().contains("7   a script ran for longer than the timeout allowed"));
+}
+
+#[test]
+fn print_argument() {
+    let args = vec![OsString::from("--color=never"), OsString::from("-p"), OsString::from("1 + 1")];
+    match parse_args(args) {
+        Ok(Options { input: Input::Code { ref code, print: true }, .. }) => {
+            assert_eq!(code, "1 + 1")
         }
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn eval_and_print() {
+    let args = vec![OsString::from("-e"), OsString::from("1"),
+                    OsString::from("-p"), OsString::from("2")];
+    match parse_args(args) {
+        Err(Error::InvalidArgument(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unexpected ok"),
     }
 }
 
 #[test]
-fn missing_argument() {
-    match do_main(None) {
-        Err(Error::MissingArgument) => (),
+fn file_arguments() {
+    let args = vec![OsString::from("-f"), OsString::from("a.js"), OsString::from("b.js")];
+    match parse_args(args) {
+        Ok(Options { ref scripts, input: Input::Script(ref source), .. }) => {
+            assert_eq!(*scripts, [Source::File(PathBuf::from("a.js"))]);
+            assert_eq!(*source, Source::File(PathBuf::from("b.js")));
+        }
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+
+    // The last file is not taken for the script.
+    let args = vec![OsString::from("-f"), OsString::from("a.js"),
+                    OsString::from("-f"), OsString::from("b.js")];
+    match parse_args(args) {
+        Ok(Options { ref scripts, input: Input::Nothing, .. }) => assert_eq!(scripts.len(), 2),
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+
+    // Without -f, only the first path is a script.
+    let args = vec![OsString::from("a.js"), OsString::from("b.js")];
+    match parse_args(args) {
+        Ok(Options { ref scripts, input: Input::Script(_), ref script_args, .. }) => {
+            assert!(scripts.is_empty());
+            assert_eq!(*script_args, ["b.js"]);
+        }
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn stdin_once() {
+    let args = vec![OsString::from("-f"), OsString::from("-"), OsString::from("-")];
+    match parse_args(args) {
+        Err(Error::InvalidArgument(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unexpected ok"),
+    }
+
+    let args = vec![OsString::from("-f"), OsString::from("-"),
+                    OsString::from("-f"), OsString::from("-")];
+    match parse_args(args) {
+        Err(Error::InvalidArgument(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unexpected ok"),
+    }
+
+    // Once is fine, and later arguments are left to the script.
+    let args = vec![OsString::from("-f"), OsString::from("-"),
+                    OsString::from("a.js"), OsString::from("-")];
+    match parse_args(args) {
+        Ok(Options { input: Input::Script(_), ref script_args, .. }) => {
+            assert_eq!(*script_args, ["-"])
+        }
+        Ok(_) => panic!("Unexpected input"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn console_json() {
+    // The script is never taken for the file to write to.
+    let args = vec![OsString::from("--console-json"), OsString::from("script.js")];
+    match parse_args(args) {
+        Ok(Options { input: Input::Script(ref source), output: ConsoleOutput::Json(None), .. }) => {
+            assert_eq!(*source, Source::File(PathBuf::from("script.js")))
+        }
+        Ok(_) => panic!("Unexpected options"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn script_arguments() {
+    use std::os::unix::ffi::OsStringExt;
+    let path = OsString::from_vec(b"script\xff.js".to_vec());
+    let args = vec![path.clone(),
+                    OsString::from("--color=never"),
+                    OsString::from_vec(b"\xff".to_vec())];
+    match parse_args(args) {
+        Ok(Options { input: Input::Script(ref source), ref script_args, .. }) => {
+            assert_eq!(*source, Source::File(PathBuf::from(path)));
+            assert_eq!(*script_args, ["--color=never", "\u{FFFD}"]);
+        }
+        Ok(_) => panic!("Unexpected input"),
         Err(error) => panic!("Unexpected error: {}", error),
-        Ok(()) => panic!("Unexpected ok"),
     }
 }
@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use error::Error;
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+use script::{Context, Source};
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// The name under which code typed into the REPL is compiled.
+const FILENAME: &'static str = "<repl>";
+
+/// The file in the home directory where the history is kept.
+const HISTORY_FILE: &'static str = ".runtime_history";
+
+fn history_path() -> Option<PathBuf> {
+    env::home_dir().map(|home| home.join(HISTORY_FILE))
+}
+
+fn report(error: &Error) {
+    let mut stderr = io::stderr();
+    let _ = writeln!(stderr, "{}", error);
+    if let Some(details) = error.details() {
+        let _ = writeln!(stderr, "{}", details);
+    }
+}
+
+/// Read lines of JavaScript from the terminal and evaluate them in
+/// `context`, printing each completion value, until end of input, `.exit`
+/// or `process.exit()`. Input continues over several lines until it forms a
+/// complete statement; `.load FILE` runs a script file instead.
+pub fn run(context: &Context) -> Result<(), Error> {
+    let mut editor = Editor::new();
+    let history = history_path();
+    if let Some(ref path) = history {
+        // There is no history the first time around.
+        let _ = editor.load_history(path);
+    }
+
+    let mut buffer = String::new();
+    let mut exit = None;
+    loop {
+        let prompt = if buffer.is_empty() { "> " } else { "... " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                // Abandon the current input, like a shell does.
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.into()),
+        };
+        editor.add_history_entry(&line);
+
+        if buffer.is_empty() {
+            let command = line.trim();
+            if command == ".exit" {
+                break;
+            }
+            if command.starts_with(".load ") {
+                let path = command[".load ".len()..].trim();
+                match context.run(&Source::File(PathBuf::from(path))) {
+                    Ok(()) => (),
+                    Err(Error::Exit(code)) => {
+                        exit = Some(code);
+                        break;
+                    }
+                    Err(error) => report(&error),
+                }
+                continue;
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !context.is_compilable_unit(&buffer) {
+            continue;
+        }
+
+        match context.evaluate_and_inspect(&buffer, FILENAME, 1) {
+            Ok(result) => println!("{}", result),
+            Err(Error::Exit(code)) => {
+                exit = Some(code);
+                break;
+            }
+            Err(error) => report(&error),
+        }
+        buffer.clear();
+    }
+
+    if let Some(ref path) = history {
+        if let Err(error) = editor.save_history(path) {
+            report(&error.into());
+        }
+    }
+    match exit {
+        Some(code) => Err(Error::Exit(code)),
+        None => Ok(()),
+    }
+}
@@ -2,7 +2,6 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use js::conversions::FromJSValConvertible;
 use js::jsapi::CallArgs;
 use js::jsapi::HandleObject;
 use js::jsapi::HandleValue;
@@ -21,47 +20,348 @@ use js::JSCLASS_IS_GLOBAL;
 use js::JSCLASS_RESERVED_SLOTS_MASK;
 use js::JSCLASS_RESERVED_SLOTS_SHIFT;
 use js::JSPROP_ENUMERATE;
+use js::rust::ToBoolean;
+use js::rust::ToNumber;
 use libc::c_char;
+use script::inspect::{DEFAULT_DEPTH, number_to_string, render, serialize, to_string};
+use script::table::{Table, build_table};
+use script::stack::{StackFrame, capture_stack, current_frame, stack_line};
 use script::reflect::{Reflectable, PrototypeID, finalize};
+use script::native::{self, NativeError};
+use std::cell::Cell;
 use std::cell::Ref;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter;
 use std::ptr;
+use std::rc::Rc;
+use time::{Timespec, get_time, precise_time_ns};
 
-pub trait ConsoleMessageHandler {
-    fn log(&self, s: String);
+/// The severity of a console message, one per `console` method.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum LogLevel {
+    Log,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// The name of the `console` method for this level.
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Log => "log",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// Whether messages of this level are diagnostics rather than normal
+    /// output.
+    pub fn is_diagnostic(self) -> bool {
+        match self {
+            LogLevel::Error | LogLevel::Warn => true,
+            LogLevel::Log | LogLevel::Info | LogLevel::Debug | LogLevel::Trace => false,
+        }
+    }
+}
+
+/// A serializable representation of a value passed to a console method.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ConsoleValue {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<ConsoleValue>),
+    Object(Vec<(String, ConsoleValue)>),
+    /// Any other value, represented by its inspected text.
+    Other(String),
 }
 
-pub struct StdoutHandler;
+/// A run of the text of a message, styled by the CSS passed to the `%c`
+/// directive that preceded it, if any.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub css: Option<String>,
+}
 
-impl ConsoleMessageHandler for StdoutHandler {
-    fn log(&self, s: String) {
-        println!("{}", s);
+impl TextSpan {
+    pub fn plain(text: String) -> TextSpan {
+        TextSpan {
+            text: text,
+            css: None,
+        }
     }
 }
 
-pub struct StoringHandler(RefCell<Vec<String>>);
+/// A single message logged through the console.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ConsoleMessage {
+    pub level: LogLevel,
+    /// The message as formatted from the arguments.
+    pub text: String,
+    /// The same text, split into differently styled runs.
+    pub spans: Vec<TextSpan>,
+    /// The arguments passed to the console method, except for
+    /// `console.table()`, whose data is in the table instead.
+    pub arguments: Vec<ConsoleValue>,
+    pub timestamp: Timespec,
+    /// The script location of the console call, if it was made from script
+    /// and the handler records call sites.
+    pub filename: Option<String>,
+    pub line: u32,
+    pub column: u32,
+    /// The number of groups the message is nested in.
+    pub group_depth: usize,
+    /// The JavaScript call stack at the call site, for methods that capture
+    /// it.
+    pub stack: Option<Vec<StackFrame>>,
+}
+
+/// The JavaScript call of a console method.
+pub struct CallSite {
+    pub arguments: Vec<ConsoleValue>,
+    /// The innermost script frame of the call, if there is one.
+    pub location: Option<StackFrame>,
+}
+
+pub trait ConsoleMessageHandler {
+    fn log(&self, message: ConsoleMessage);
+
+    /// Whether the handler wants the script location of each message.
+    /// Finding it means walking the JavaScript stack, so it is only done when
+    /// asked for.
+    fn records_call_site(&self) -> bool {
+        false
+    }
+
+    /// Handle the start of a group labelled `message`, which will contain
+    /// the messages logged one level deeper until the matching `group_end`.
+    fn group_start(&self, message: ConsoleMessage, _collapsed: bool) {
+        self.log(message);
+    }
+
+    /// Handle the end of the group that was started at `depth`.
+    fn group_end(&self, _depth: usize) {}
+
+    /// Handle a call to `console.table()`. By default, the table is logged
+    /// as aligned text.
+    fn table(&self, mut message: ConsoleMessage, table: Table) {
+        message.text = table.render();
+        message.spans = vec![TextSpan::plain(message.text.clone())];
+        self.log(message);
+    }
+}
+
+/// Lay out `text`, the rendering of `message`, for a text stream: indent it
+/// by the group depth, and follow it by the stack trace, if any.
+pub fn layout(message: &ConsoleMessage, text: &str) -> String {
+    let indent = iter::repeat("  ").take(message.group_depth).collect::<String>();
+    let mut lines = text.lines()
+                        .map(|line| format!("{}{}", indent, line))
+                        .collect::<Vec<_>>();
+    if let Some(ref stack) = message.stack {
+        for frame in stack {
+            lines.push(format!("{}{}", indent, stack_line(frame)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// A record of a call to a `ConsoleMessageHandler`.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ConsoleEntry {
+    Message(ConsoleMessage),
+    GroupStart {
+        message: ConsoleMessage,
+        collapsed: bool,
+    },
+    GroupEnd,
+    Table(ConsoleMessage, Table),
+}
+
+/// A console handler that keeps everything it is given, so that embedders
+/// and tests can inspect what a script logged.
+#[derive(Default)]
+pub struct StoringHandler(RefCell<Vec<ConsoleEntry>>);
 
 impl StoringHandler {
-    fn get(&self) -> Ref<[String]> {
+    pub fn new() -> StoringHandler {
+        StoringHandler::default()
+    }
+
+    /// The entries recorded so far, in order.
+    pub fn get(&self) -> Ref<[ConsoleEntry]> {
         Ref::map(self.0.borrow(), |x| &**x)
     }
 }
 
 impl ConsoleMessageHandler for StoringHandler {
-    fn log(&self, s: String) {
-        self.0.borrow_mut().push(s);
+    fn log(&self, message: ConsoleMessage) {
+        self.0.borrow_mut().push(ConsoleEntry::Message(message));
+    }
+
+    fn records_call_site(&self) -> bool {
+        true
+    }
+
+    fn group_start(&self, message: ConsoleMessage, collapsed: bool) {
+        self.0.borrow_mut().push(ConsoleEntry::GroupStart {
+            message: message,
+            collapsed: collapsed,
+        });
+    }
+
+    fn group_end(&self, _depth: usize) {
+        self.0.borrow_mut().push(ConsoleEntry::GroupEnd);
+    }
+
+    fn table(&self, message: ConsoleMessage, table: Table) {
+        self.0.borrow_mut().push(ConsoleEntry::Table(message, table));
     }
 }
 
-pub struct Console(Box<ConsoleMessageHandler>);
+pub struct Console {
+    handler: Rc<ConsoleMessageHandler>,
+    /// The start time of each running timer, in nanoseconds.
+    timers: RefCell<HashMap<String, u64>>,
+    counters: RefCell<HashMap<String, u32>>,
+    group_depth: Cell<usize>,
+}
 
 impl Console {
-    pub fn new(handler: Box<ConsoleMessageHandler>) -> Console {
-        Console(handler)
+    pub fn new(handler: Rc<ConsoleMessageHandler>) -> Console {
+        Console {
+            handler: handler,
+            timers: RefCell::new(HashMap::new()),
+            counters: RefCell::new(HashMap::new()),
+            group_depth: Cell::new(0),
+        }
+    }
+
+    fn message(&self,
+               call: &CallSite,
+               level: LogLevel,
+               spans: Vec<TextSpan>,
+               stack: Option<Vec<StackFrame>>)
+               -> ConsoleMessage {
+        let text = spans.iter().map(|span| &*span.text).collect();
+        let (filename, line, column) = match call.location {
+            Some(ref frame) => (Some(frame.filename.clone()), frame.line, frame.column),
+            None => (None, 0, 0),
+        };
+        ConsoleMessage {
+            level: level,
+            text: text,
+            spans: spans,
+            arguments: call.arguments.clone(),
+            timestamp: get_time(),
+            filename: filename,
+            line: line,
+            column: column,
+            group_depth: self.group_depth.get(),
+            stack: stack,
+        }
+    }
+
+    pub fn log(&self, call: &CallSite, level: LogLevel, text: String) {
+        self.log_spans(call, level, vec![TextSpan::plain(text)], None);
+    }
+
+    pub fn log_spans(&self,
+                     call: &CallSite,
+                     level: LogLevel,
+                     spans: Vec<TextSpan>,
+                     stack: Option<Vec<StackFrame>>) {
+        self.handler.log(self.message(call, level, spans, stack));
     }
 
-    pub fn log(&self, message: String) {
-        self.0.log(message);
+    pub fn table(&self, call: &CallSite, table: Table) {
+        self.handler.table(self.message(call, LogLevel::Log, vec![], None), table);
+    }
+
+    pub fn group(&self, call: &CallSite, label: Vec<TextSpan>, collapsed: bool) {
+        let depth = self.group_depth.get();
+        self.handler.group_start(self.message(call, LogLevel::Log, label, None), collapsed);
+        self.group_depth.set(depth + 1);
+    }
+
+    pub fn group_end(&self) {
+        let depth = self.group_depth.get();
+        if depth > 0 {
+            self.group_depth.set(depth - 1);
+            self.handler.group_end(depth - 1);
+        }
+    }
+
+    pub fn time(&self, call: &CallSite, label: String) {
+        let mut timers = self.timers.borrow_mut();
+        if timers.contains_key(&label) {
+            self.log(call, LogLevel::Warn, format!("Timer '{}' already exists", label));
+            return;
+        }
+        timers.insert(label, precise_time_ns());
+    }
+
+    fn elapsed(&self, call: &CallSite, label: &str) -> Option<String> {
+        match self.timers.borrow().get(label) {
+            Some(&start) => {
+                let elapsed = (precise_time_ns() - start) as f64 / 1_000_000.;
+                Some(format!("{}: {:.3}ms", label, elapsed))
+            }
+            None => {
+                self.log(call, LogLevel::Warn, format!("Timer '{}' does not exist", label));
+                None
+            }
+        }
+    }
+
+    pub fn time_log(&self, call: &CallSite, label: String, data: String) {
+        if let Some(mut text) = self.elapsed(call, &label) {
+            if !data.is_empty() {
+                text.push(' ');
+                text.push_str(&data);
+            }
+            self.log(call, LogLevel::Info, text);
+        }
+    }
+
+    pub fn time_end(&self, call: &CallSite, label: String) {
+        if let Some(text) = self.elapsed(call, &label) {
+            self.timers.borrow_mut().remove(&label);
+            self.log(call, LogLevel::Info, text);
+        }
+    }
+
+    pub fn count(&self, call: &CallSite, label: String) {
+        let count = {
+            let mut counters = self.counters.borrow_mut();
+            let count = counters.entry(label.clone()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        self.log(call, LogLevel::Info, format!("{}: {}", label, count));
+    }
+
+    pub fn count_reset(&self, call: &CallSite, label: String) {
+        let found = match self.counters.borrow_mut().get_mut(&label) {
+            Some(count) => {
+                *count = 0;
+                true
+            }
+            None => false,
+        };
+        if !found {
+            self.log(call, LogLevel::Warn, format!("Count for '{}' does not exist", label));
+        }
     }
 }
 
@@ -110,6 +410,118 @@ const METHODS: &'static [JSFunctionSpec] = &[
         flags: JSPROP_ENUMERATE as u16,
         selfHostedName: 0 as *const c_char
     },
+    JSFunctionSpec {
+        name: b"error\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_error_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"warn\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_warn_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"info\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_info_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"debug\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_debug_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"trace\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_trace_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"dir\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_dir_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"time\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_time_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"timeLog\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_time_log_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"timeEnd\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_time_end_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"count\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_count_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"countReset\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_count_reset_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"group\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_group_native), info: 0 as *const _},
+        nargs: 0,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"groupCollapsed\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_group_collapsed_native), info: 0 as *const _},
+        nargs: 0,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"groupEnd\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_group_end_native), info: 0 as *const _},
+        nargs: 0,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"assert\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_assert_native), info: 0 as *const _},
+        nargs: 0,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
+    JSFunctionSpec {
+        name: b"table\0" as *const u8 as *const c_char,
+        call: JSNativeWrapper {op: Some(console_table_native), info: 0 as *const _},
+        nargs: 1,
+        flags: JSPROP_ENUMERATE as u16,
+        selfHostedName: 0 as *const c_char
+    },
     JSFunctionSpec {
         name: 0 as *const c_char,
         call: JSNativeWrapper { op: None, info: 0 as *const _ },
@@ -140,9 +552,9 @@ impl Reflectable for Console {
 
 pub unsafe fn create_console(cx: *mut JSContext,
                              scope: HandleObject,
-                             handler: Box<ConsoleMessageHandler>,
+                             handler: Rc<ConsoleMessageHandler>,
                              rval: MutableHandleObject)
-                             -> Result<(), ()> {
+                             -> Result<(), NativeError> {
     let console = Box::new(Console::new(handler));
     assert!(!scope.get().is_null());
     assert!(((*JS_GetClass(scope.get())).flags & JSCLASS_IS_GLOBAL) != 0);
@@ -154,24 +566,344 @@ pub unsafe fn create_console(cx: *mut JSContext,
     assert!(!proto.ptr.is_null());
 
     rval.set(JS_NewObjectWithGivenProto(cx, &CLASS as *const _, proto.handle()));
-    assert!(!rval.get().is_null());
+    if rval.get().is_null() {
+        return Err(NativeError::Exception);
+    }
 
     console.init(rval.get());
     Ok(())
 }
 
-unsafe fn to_string(cx: *mut JSContext, value: HandleValue) -> Result<String, ()> {
-    String::from_jsval(cx, value, ())
+/// Format the substitution directive `specifier` with `value`, following the
+/// Formatter operation of the Console Standard.
+unsafe fn format_directive(cx: *mut JSContext,
+                           specifier: char,
+                           value: HandleValue,
+                           serialized: &ConsoleValue)
+                           -> Result<String, NativeError> {
+    match specifier {
+        'd' | 'i' => {
+            let number = try!(ToNumber(cx, value).map_err(|()| NativeError::Exception));
+            number_to_string(cx, number.trunc())
+        }
+        'f' => {
+            let number = try!(ToNumber(cx, value).map_err(|()| NativeError::Exception));
+            number_to_string(cx, number)
+        }
+        'o' | 'O' => render(cx, serialized),
+        // ToString throws for symbols, unlike `String()`.
+        _ if value.is_symbol() => render(cx, serialized),
+        _ => to_string(cx, value),
+    }
+}
+
+/// Format the arguments passed to a console method, starting at index
+/// `start`, into a single message. If the first of those is a string, it is
+/// treated as a format string, in which `%c` starts a new run of text styled
+/// by its argument; the remaining arguments are appended, separated by
+/// spaces. Inspected arguments are rendered from `arguments`, their
+/// serialized forms, rather than looked at again.
+unsafe fn format_spans(cx: *mut JSContext,
+                       args: &CallArgs,
+                       arguments: &[ConsoleValue],
+                       start: u32)
+                       -> Result<Vec<TextSpan>, NativeError> {
+    let argc = args._base.argc_;
+    let mut spans = vec![TextSpan::plain(String::new())];
+    let mut next = start;
+    if argc > start && args.get(start).is_string() {
+        let format = try!(to_string(cx, args.get(start)));
+        next = start + 1;
+
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                spans.last_mut().unwrap().text.push(c);
+                continue;
+            }
+            match chars.peek().cloned() {
+                Some('%') => {
+                    chars.next();
+                    spans.last_mut().unwrap().text.push('%');
+                }
+                Some('c') if next < argc => {
+                    chars.next();
+                    let css = try!(to_string(cx, args.get(next)));
+                    spans.push(TextSpan {
+                        text: String::new(),
+                        css: Some(css),
+                    });
+                    next += 1;
+                }
+                Some(specifier) if "sdifoO".contains(specifier) && next < argc => {
+                    chars.next();
+                    let text = try!(format_directive(cx,
+                                                     specifier,
+                                                     args.get(next),
+                                                     &arguments[next as usize]));
+                    spans.last_mut().unwrap().text.push_str(&text);
+                    next += 1;
+                }
+                _ => spans.last_mut().unwrap().text.push('%'),
+            }
+        }
+        // Styles only apply to the format string itself.
+        spans.push(TextSpan::plain(String::new()));
+    }
+
+    for i in next..argc {
+        let text = try!(render(cx, &arguments[i as usize]));
+        let span = spans.last_mut().unwrap();
+        if i > start {
+            span.text.push(' ');
+        }
+        span.text.push_str(&text);
+    }
+    spans.retain(|span| !span.text.is_empty());
+    Ok(spans)
+}
+
+/// Format the arguments passed to a console method, starting at index
+/// `start`, into a single unstyled message.
+unsafe fn format_arguments(cx: *mut JSContext,
+                           args: &CallArgs,
+                           arguments: &[ConsoleValue],
+                           start: u32)
+                           -> Result<String, NativeError> {
+    let spans = try!(format_spans(cx, args, arguments, start));
+    Ok(spans.iter().map(|span| &*span.text).collect())
+}
+
+/// The script location of the current console call, if the handler of
+/// `console` records call sites.
+unsafe fn location(cx: *mut JSContext,
+                   console: *const Console)
+                   -> Result<Option<StackFrame>, NativeError> {
+    if !(*console).handler.records_call_site() {
+        return Ok(None);
+    }
+    current_frame(cx)
+}
+
+/// Describe the current call of a console method. Each argument is
+/// serialized here, once; the text of the message is rendered from these
+/// values, so that getters on the arguments run only once.
+unsafe fn call_site(cx: *mut JSContext,
+                    console: *const Console,
+                    args: &CallArgs)
+                    -> Result<CallSite, NativeError> {
+    let mut arguments = vec![];
+    for i in 0..args._base.argc_ {
+        arguments.push(try!(serialize(cx, args.get(i), DEFAULT_DEPTH)));
+    }
+    Ok(CallSite {
+        arguments: arguments,
+        location: try!(location(cx, console)),
+    })
+}
+
+unsafe fn console_message(cx: *mut JSContext,
+                          args: &CallArgs,
+                          level: LogLevel)
+                          -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    let spans = try!(format_spans(cx, args, &call.arguments, 0));
+    let stack = if level == LogLevel::Trace {
+        Some(try!(capture_stack(cx)))
+    } else {
+        None
+    };
+    (*console).log_spans(&call, level, spans, stack);
+    Ok(())
+}
+
+unsafe fn console_assert(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    if ToBoolean(args.get(0)) {
+        return Ok(());
+    }
+    let call = try!(call_site(cx, console, args));
+    let mut spans = try!(format_spans(cx, args, &call.arguments, 1));
+    let prefix = if spans.is_empty() { "Assertion failed" } else { "Assertion failed: " };
+    spans.insert(0, TextSpan::plain(prefix.to_owned()));
+    let stack = try!(capture_stack(cx));
+    (*console).log_spans(&call, LogLevel::Error, spans, Some(stack));
+    Ok(())
+}
+
+unsafe fn console_dir(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    let message = try!(render(cx, call.arguments.get(0).unwrap_or(&ConsoleValue::Undefined)));
+    (*console).log(&call, LogLevel::Log, message);
+    Ok(())
+}
+
+/// The label passed as the first argument to a timer or counter method.
+unsafe fn label(cx: *mut JSContext, args: &CallArgs) -> Result<String, NativeError> {
+    let value = args.get(0);
+    if value.is_undefined() {
+        Ok("default".to_owned())
+    } else {
+        to_string(cx, value)
+    }
+}
+
+unsafe fn console_time(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    (*console).time(&call, try!(label(cx, args)));
+    Ok(())
+}
+
+unsafe fn console_time_log(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    let label = try!(label(cx, args));
+    let data = try!(format_arguments(cx, args, &call.arguments, 1));
+    (*console).time_log(&call, label, data);
+    Ok(())
+}
+
+unsafe fn console_time_end(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    (*console).time_end(&call, try!(label(cx, args)));
+    Ok(())
+}
+
+unsafe fn console_count(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    (*console).count(&call, try!(label(cx, args)));
+    Ok(())
+}
+
+unsafe fn console_count_reset(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    (*console).count_reset(&call, try!(label(cx, args)));
+    Ok(())
 }
 
-unsafe fn console_log(cx: *mut JSContext, args: &CallArgs) -> Result<(), ()> {
-    let console = try!(Console::from_value(cx, args.thisv()));
-    let message = try!(to_string(cx, args.get(0)));
-    (*console).log(message);
+unsafe fn console_table(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    match try!(build_table(cx, args.get(0), args.get(1))) {
+        Some(table) => {
+            let call = CallSite {
+                arguments: vec![],
+                location: try!(location(cx, console)),
+            };
+            (*console).table(&call, table);
+        }
+        None => {
+            let call = try!(call_site(cx, console, args));
+            let text = try!(format_arguments(cx, args, &call.arguments, 0));
+            (*console).log(&call, LogLevel::Log, text);
+        }
+    }
+    Ok(())
+}
+
+unsafe fn console_group(cx: *mut JSContext,
+                        args: &CallArgs,
+                        collapsed: bool)
+                        -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    let call = try!(call_site(cx, console, args));
+    let label = try!(format_spans(cx, args, &call.arguments, 0));
+    (*console).group(&call, label, collapsed);
+    Ok(())
+}
+
+unsafe fn console_group_end(_cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let console = try!(Console::from_value(args.thisv()));
+    (*console).group_end();
     Ok(())
 }
 
 unsafe extern "C" fn console_log_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    console_log(cx, &args).is_ok()
+    native::call(cx, || console_message(cx, &args, LogLevel::Log))
+}
+
+unsafe extern "C" fn console_error_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_message(cx, &args, LogLevel::Error))
+}
+
+unsafe extern "C" fn console_warn_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_message(cx, &args, LogLevel::Warn))
+}
+
+unsafe extern "C" fn console_info_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_message(cx, &args, LogLevel::Info))
+}
+
+unsafe extern "C" fn console_debug_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_message(cx, &args, LogLevel::Debug))
+}
+
+unsafe extern "C" fn console_trace_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_message(cx, &args, LogLevel::Trace))
+}
+
+unsafe extern "C" fn console_dir_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_dir(cx, &args))
+}
+
+unsafe extern "C" fn console_time_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_time(cx, &args))
+}
+
+unsafe extern "C" fn console_time_log_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_time_log(cx, &args))
+}
+
+unsafe extern "C" fn console_time_end_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_time_end(cx, &args))
+}
+
+unsafe extern "C" fn console_count_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_count(cx, &args))
+}
+
+unsafe extern "C" fn console_count_reset_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_count_reset(cx, &args))
+}
+
+unsafe extern "C" fn console_group_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_group(cx, &args, false))
+}
+
+unsafe extern "C" fn console_group_collapsed_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_group(cx, &args, true))
+}
+
+unsafe extern "C" fn console_group_end_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_group_end(cx, &args))
+}
+
+unsafe extern "C" fn console_assert_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_assert(cx, &args))
+}
+
+unsafe extern "C" fn console_table_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || console_table(cx, &args))
 }
@@ -0,0 +1,186 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::jsapi::HandleObject;
+use js::jsapi::HandleValue;
+use js::jsapi::JSContext;
+use js::jsapi::JS_ClearPendingException;
+use js::jsapi::JS_ErrorFromException;
+use js::jsapi::JS_GetPendingException;
+use js::jsapi::JS_GetProperty;
+use js::jsapi::JS_IsExceptionPending;
+use js::jsapi::RootedObject;
+use js::jsapi::RootedValue;
+use js::jsval::UndefinedValue;
+use libc::c_char;
+use script::inspect::{DEFAULT_DEPTH, inspect, to_string};
+use script::stack::stack_lines;
+use std::ffi::CStr;
+use std::fmt::{self, Display, Formatter};
+use std::iter;
+
+/// A JavaScript exception that was thrown and not caught.
+#[derive(Debug)]
+pub struct Exception {
+    /// The name of the error, e.g., `ReferenceError`.
+    pub name: String,
+    pub message: String,
+    pub filename: String,
+    pub line: u32,
+    /// The zero-based column within `line`.
+    pub column: u32,
+    /// The JavaScript stack at the point the error was created, if known.
+    pub stack: Option<String>,
+    /// The text of `line`, if the source is available.
+    pub source_line: Option<String>,
+}
+
+impl Exception {
+    /// Describe the exception in more detail than `Display` does: the
+    /// offending source line with a caret under the column, in the style of
+    /// rustc's diagnostics, followed by the stack.
+    pub fn details(&self) -> String {
+        let mut details = vec![];
+        if let Some(ref source) = self.source_line {
+            let line_number = self.line.to_string();
+            let gutter = iter::repeat(' ').take(line_number.len()).collect::<String>();
+            // The column counts UTF-16 code units. Keep tabs, so the caret
+            // lines up with the source.
+            let mut padding = String::new();
+            let mut units = 0;
+            for c in source.chars() {
+                if units >= self.column as usize {
+                    break;
+                }
+                units += c.len_utf16();
+                padding.push(if c == '\t' { '\t' } else { ' ' });
+            }
+            details.push(format!("{} |", gutter));
+            details.push(format!("{} | {}", line_number, source));
+            details.push(format!("{} | {}^", gutter, padding));
+        }
+        if let Some(ref stack) = self.stack {
+            details.extend(stack_lines(stack));
+        }
+        details.join("\n")
+    }
+}
+
+impl Display for Exception {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
+        if !self.filename.is_empty() {
+            // Columns are shown one-based, like lines.
+            try!(write!(formatter, "{}:{}:{}: ", self.filename, self.line, self.column + 1));
+        }
+        write!(formatter, "{}: {}", self.name, self.message)
+    }
+}
+
+/// Take the exception pending on `cx`, if any, and clear it. The caller must
+/// have entered a compartment.
+pub unsafe fn take_pending_exception(cx: *mut JSContext) -> Option<Exception> {
+    if !JS_IsExceptionPending(cx) {
+        return None;
+    }
+
+    let mut value = RootedValue::new(cx, UndefinedValue());
+    let got = JS_GetPendingException(cx, value.handle_mut());
+    JS_ClearPendingException(cx);
+    if !got {
+        return None;
+    }
+
+    let exception = exception_from_value(cx, value.handle());
+    // Looking at the exception may itself have thrown.
+    JS_ClearPendingException(cx);
+    Some(exception)
+}
+
+unsafe fn string_property(cx: *mut JSContext,
+                          object: HandleObject,
+                          name: &'static [u8])
+                          -> Option<String> {
+    let mut value = RootedValue::new(cx, UndefinedValue());
+    if !JS_GetProperty(cx, object, name.as_ptr() as *const c_char, value.handle_mut()) ||
+       !value.ptr.is_string() {
+        return None;
+    }
+    to_string(cx, value.handle()).ok()
+}
+
+unsafe fn exception_from_value(cx: *mut JSContext, value: HandleValue) -> Exception {
+    if value.is_object() {
+        let object = RootedObject::new(cx, value.to_object());
+        let report = JS_ErrorFromException(cx, object.handle());
+        if !report.is_null() {
+            let filename = if (*report).filename.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr((*report).filename).to_string_lossy().into_owned()
+            };
+            return Exception {
+                name: string_property(cx, object.handle(), b"name\0")
+                          .unwrap_or_else(|| "Error".to_owned()),
+                message: string_property(cx, object.handle(), b"message\0")
+                             .unwrap_or_else(String::new),
+                filename: filename,
+                line: (*report).lineno,
+                column: (*report).column,
+                stack: string_property(cx, object.handle(), b"stack\0"),
+                source_line: None,
+            };
+        }
+    }
+
+    // Something other than an error object was thrown.
+    Exception {
+        name: "uncaught exception".to_owned(),
+        message: inspect(cx, value, DEFAULT_DEPTH).unwrap_or_else(|_| "<unknown>".to_owned()),
+        filename: String::new(),
+        line: 0,
+        column: 0,
+        stack: None,
+        source_line: None,
+    }
+}
+
+#[test]
+fn details() {
+    let exception = Exception {
+        name: "SyntaxError".to_owned(),
+        message: "missing ) after argument list".to_owned(),
+        filename: "test.js".to_owned(),
+        line: 12,
+        column: 5,
+        stack: None,
+        source_line: Some("\tf(a b);".to_owned()),
+    };
+    assert_eq!(exception.details(),
+               ["   |", "12 | \tf(a b);", "   | \t    ^"].join("\n"));
+
+    // The astral character is two UTF-16 code units, but a single character.
+    let exception = Exception {
+        column: 14,
+        source_line: Some("var s = '\u{1f600}'; x(".to_owned()),
+        ..exception
+    };
+    assert_eq!(exception.details(),
+               ["   |", "12 | var s = '\u{1f600}'; x(", "   |              ^"].join("\n"));
+}
+
+#[test]
+fn display() {
+    let mut exception = Exception {
+        name: "ReferenceError".to_owned(),
+        message: "x is not defined".to_owned(),
+        filename: "test.js".to_owned(),
+        line: 3,
+        column: 0,
+        stack: None,
+        source_line: None,
+    };
+    assert_eq!(exception.to_string(), "test.js:3:1: ReferenceError: x is not defined");
+    exception.filename = String::new();
+    assert_eq!(exception.to_string(), "ReferenceError: x is not defined");
+}
@@ -4,22 +4,30 @@
 
 use js::jsapi::CallArgs;
 use js::jsapi::CompartmentOptions;
+use js::jsapi::CurrentGlobalOrNull;
+use js::jsapi::HandleObject;
 use js::jsapi::JSAutoCompartment;
 use js::jsapi::JSClass;
 use js::jsapi::JSContext;
+use js::jsapi::JS_DefineFunction;
 use js::jsapi::JS_FireOnNewGlobalObject;
+use js::jsapi::JS_GetProperty;
+use js::jsapi::JS_GetReservedSlot;
 use js::jsapi::JS_GlobalObjectTraceHook;
 use js::jsapi::JS_InitStandardClasses;
 use js::jsapi::JSNativeWrapper;
 use js::jsapi::JS_NewGlobalObject;
+use js::jsapi::JS_NewPlainObject;
 use js::jsapi::JSObject;
 use js::jsapi::JSPropertySpec;
 use js::jsapi::JS_SetPrototype;
+use js::jsapi::JS_SetReservedSlot;
 use js::jsapi::JSTraceOp;
 use js::jsapi::JSVersion;
 use js::jsapi::MutableHandleObject;
 use js::jsapi::OnNewGlobalHookOption;
 use js::jsapi::RootedObject;
+use js::jsapi::RootedValue;
 use js::jsapi::Value;
 use js::JSCLASS_GLOBAL_SLOT_COUNT;
 use js::JSCLASS_IS_GLOBAL;
@@ -27,17 +35,36 @@ use js::JSCLASS_RESERVED_SLOTS_MASK;
 use js::JSCLASS_RESERVED_SLOTS_SHIFT;
 use js::JSPROP_ENUMERATE;
 use js::JSPROP_SHARED;
-use js::jsval::ObjectValue;
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::ToInt32;
 use libc::c_char;
-use script::console;
-use script::reflect::{Reflectable, PrototypeID, finalize, initialize_global};
+use script::console::{self, ConsoleMessageHandler};
+use script::native::{self, NativeError};
+use script::reflect::{DOM_PROTOTYPE_SLOT, Reflectable, PrototypeID, finalize, initialize_global};
+use std::cell::Cell;
 use std::ptr;
 use std::env;
 use std::process;
+use std::rc::Rc;
 use js::jsapi::JSFunctionSpec;
-use js::conversions::FromJSValConvertible;
+use js::conversions::{FromJSValConvertible, ToJSValConvertible};
 
-pub struct Global(usize);
+/// The reserved slot of the global object that holds its `console` object.
+/// Like any object stored in a reserved slot, it is traced by the GC.
+const CONSOLE_SLOT: u32 = DOM_PROTOTYPE_SLOT + 1;
+/// The reserved slot that holds the `scriptArgs` array.
+const SCRIPT_ARGS_SLOT: u32 = DOM_PROTOTYPE_SLOT + 2;
+/// The reserved slot that holds the `process` object.
+const PROCESS_SLOT: u32 = DOM_PROTOTYPE_SLOT + 3;
+
+pub struct Global {
+    /// The handler for the messages logged through `console`.
+    console_handler: Rc<ConsoleMessageHandler>,
+    /// The arguments exposed as `scriptArgs`.
+    script_args: Vec<String>,
+    /// The exit code passed to `process.exit()`, once it has been called.
+    exit_code: Cell<Option<i32>>,
+}
 
 impl Global {
     fn launch_browser(&self, url: String) {
@@ -51,7 +78,7 @@ impl Global {
 static CLASS: JSClass = JSClass {
     name: b"Global\0" as *const u8 as *const c_char,
     flags: JSCLASS_IS_GLOBAL |
-           (((JSCLASS_GLOBAL_SLOT_COUNT + 1) & JSCLASS_RESERVED_SLOTS_MASK) <<
+           (((JSCLASS_GLOBAL_SLOT_COUNT + 4) & JSCLASS_RESERVED_SLOTS_MASK) <<
             JSCLASS_RESERVED_SLOTS_SHIFT),
     addProperty: None,
     delProperty: None,
@@ -93,6 +120,18 @@ const ATTRIBUTES: &'static [JSPropertySpec] = &[
         getter: JSNativeWrapper { op: Some(get_console_native), info: 0 as *const _ },
         setter: JSNativeWrapper { op: None, info: 0 as *const _ }
     },
+    JSPropertySpec {
+        name: b"scriptArgs\0" as *const u8 as *const c_char,
+        flags: ((JSPROP_SHARED | JSPROP_ENUMERATE) & 0xFF) as u8,
+        getter: JSNativeWrapper { op: Some(get_script_args_native), info: 0 as *const _ },
+        setter: JSNativeWrapper { op: None, info: 0 as *const _ }
+    },
+    JSPropertySpec {
+        name: b"process\0" as *const u8 as *const c_char,
+        flags: ((JSPROP_SHARED | JSPROP_ENUMERATE) & 0xFF) as u8,
+        getter: JSNativeWrapper { op: Some(get_process_native), info: 0 as *const _ },
+        setter: JSNativeWrapper { op: None, info: 0 as *const _ }
+    },
     JSPropertySpec {
         name: 0 as *const c_char,
         flags: 0,
@@ -140,33 +179,145 @@ impl Reflectable for Global {
     }
 }
 
-unsafe fn get_console(cx: *mut JSContext, args: &CallArgs) -> Result<(), ()> {
+unsafe fn get_console(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let global = try!(Global::from_value(args.thisv()));
     let thisv = args.thisv();
     let scope = RootedObject::new(cx, thisv.to_object());
+    let cached = JS_GetReservedSlot(scope.ptr, CONSOLE_SLOT);
+    if cached.is_object() {
+        args.rval().set(cached);
+        return Ok(());
+    }
+
     let mut rval = RootedObject::new(cx, ptr::null_mut());
     try!(console::create_console(cx,
                                  scope.handle(),
-                                 Box::new(console::StdoutHandler),
+                                 (*global).console_handler.clone(),
                                  rval.handle_mut()));
+    JS_SetReservedSlot(scope.ptr, CONSOLE_SLOT, ObjectValue(&*rval.ptr));
     args.rval().set(ObjectValue(&*rval.ptr));
     Ok(())
 }
 
 unsafe extern "C" fn get_console_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    get_console(cx, &args).is_ok()
+    native::call(cx, || get_console(cx, &args))
 }
 
-unsafe fn launch_browser(cx: *mut JSContext, args: &CallArgs) -> Result<(), ()> {
-    let global = try!(Global::from_value(cx, args.thisv()));
-    let url = try!(String::from_jsval(cx, args.get(0), ()));
+unsafe fn get_script_args(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let global = try!(Global::from_value(args.thisv()));
+    let thisv = args.thisv();
+    let scope = RootedObject::new(cx, thisv.to_object());
+    let cached = JS_GetReservedSlot(scope.ptr, SCRIPT_ARGS_SLOT);
+    if cached.is_object() {
+        args.rval().set(cached);
+        return Ok(());
+    }
+
+    (*global).script_args.to_jsval(cx, args.rval());
+    if !args.rval().get().is_object() {
+        return Err(NativeError::Exception);
+    }
+    JS_SetReservedSlot(scope.ptr, SCRIPT_ARGS_SLOT, args.rval().get());
+    Ok(())
+}
+
+unsafe extern "C" fn get_script_args_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || get_script_args(cx, &args))
+}
+
+unsafe fn get_process(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    try!(Global::from_value(args.thisv()));
+    let thisv = args.thisv();
+    let scope = RootedObject::new(cx, thisv.to_object());
+    let cached = JS_GetReservedSlot(scope.ptr, PROCESS_SLOT);
+    if cached.is_object() {
+        args.rval().set(cached);
+        return Ok(());
+    }
+
+    let process = RootedObject::new(cx, JS_NewPlainObject(cx));
+    if process.ptr.is_null() {
+        return Err(NativeError::Exception);
+    }
+    if JS_DefineFunction(cx,
+                         process.handle(),
+                         b"exit\0" as *const u8 as *const c_char,
+                         Some(process_exit_native),
+                         1,
+                         JSPROP_ENUMERATE)
+           .is_null() {
+        return Err(NativeError::Exception);
+    }
+    JS_SetReservedSlot(scope.ptr, PROCESS_SLOT, ObjectValue(&*process.ptr));
+    args.rval().set(ObjectValue(&*process.ptr));
+    Ok(())
+}
+
+unsafe extern "C" fn get_process_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || get_process(cx, &args))
+}
+
+unsafe fn launch_browser(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let global = try!(Global::from_value(args.thisv()));
+    let url = try!(String::from_jsval(cx, args.get(0), ()).map_err(|()| NativeError::Exception));
     (*global).launch_browser(url);
     Ok(())
 }
 
 unsafe extern "C" fn launch_browser_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
     let args = CallArgs::from_vp(vp, argc);
-    launch_browser(cx, &args).is_ok()
+    native::call(cx, || launch_browser(cx, &args))
+}
+
+unsafe fn process_exit(cx: *mut JSContext, args: &CallArgs) -> Result<(), NativeError> {
+    let global = RootedObject::new(cx, CurrentGlobalOrNull(cx));
+    let code = if args.get(0).is_undefined() {
+        try!(exit_code(cx, global.handle()))
+    } else {
+        try!(ToInt32(cx, args.get(0)).map_err(|()| NativeError::Exception))
+    };
+    (*Global::from_reflector(global.ptr)).exit_code.set(Some(code));
+    Err(NativeError::Terminate)
+}
+
+unsafe extern "C" fn process_exit_native(cx: *mut JSContext, argc: u32, vp: *mut Value) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    native::call(cx, || process_exit(cx, &args))
+}
+
+/// The exit code passed to `process.exit()`, if the script called it.
+pub unsafe fn requested_exit(global: *mut JSObject) -> Option<i32> {
+    (*Global::from_reflector(global)).exit_code.get()
+}
+
+/// The exit code the script set as `process.exitCode`, or 0.
+pub unsafe fn exit_code(cx: *mut JSContext, global: HandleObject) -> Result<i32, NativeError> {
+    let mut process_value = RootedValue::new(cx, UndefinedValue());
+    if !JS_GetProperty(cx,
+                       global,
+                       b"process\0" as *const u8 as *const c_char,
+                       process_value.handle_mut()) {
+        return Err(NativeError::Exception);
+    }
+    if !process_value.ptr.is_object() {
+        return Ok(0);
+    }
+
+    let process = RootedObject::new(cx, process_value.ptr.to_object());
+    let mut code = RootedValue::new(cx, UndefinedValue());
+    if !JS_GetProperty(cx,
+                       process.handle(),
+                       b"exitCode\0" as *const u8 as *const c_char,
+                       code.handle_mut()) {
+        return Err(NativeError::Exception);
+    }
+    if code.ptr.is_undefined() {
+        return Ok(0);
+    }
+    ToInt32(cx, code.handle()).map_err(|()| NativeError::Exception)
 }
 
 /// Create a DOM global object with the given class.
@@ -198,8 +349,19 @@ pub fn create_dom_global(cx: *mut JSContext,
 }
 
 
-pub unsafe fn create(cx: *mut JSContext, rval: MutableHandleObject) {
-    rval.set(create_dom_global(cx, &CLASS, Box::new(Global(0)), None));
+/// Create the global object, logging console messages to `console_handler`
+/// and exposing `script_args` as `scriptArgs`, along with a `process` object
+/// through which the script can set the exit code.
+pub unsafe fn create(cx: *mut JSContext,
+                     console_handler: Rc<ConsoleMessageHandler>,
+                     script_args: Vec<String>,
+                     rval: MutableHandleObject) {
+    let global = Box::new(Global {
+        console_handler: console_handler,
+        script_args: script_args,
+        exit_code: Cell::new(None),
+    });
+    rval.set(create_dom_global(cx, &CLASS, global, None));
     let _ac = JSAutoCompartment::new(cx, rval.handle().get());
     let mut proto = RootedObject::new(cx, ptr::null_mut());
     Global::get_prototype_object(cx, rval.handle(), proto.handle_mut());
@@ -0,0 +1,468 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::conversions::FromJSValConvertible;
+use js::glue::CreateAutoIdVector;
+use js::glue::DestroyAutoIdVector;
+use js::glue::SliceAutoIdVector;
+use js::jsapi::GetPropertyKeys;
+use js::jsapi::HandleId;
+use js::jsapi::HandleObject;
+use js::jsapi::HandleValue;
+use js::jsapi::HandleValueArray;
+use js::jsapi::JSContext;
+use js::jsapi::JS_CallFunctionName;
+use js::jsapi::JS_GetArrayLength;
+use js::jsapi::JS_GetClass;
+use js::jsapi::JS_GetElement;
+use js::jsapi::JS_GetProperty;
+use js::jsapi::JS_GetPropertyById;
+use js::jsapi::JS_IdToValue;
+use js::jsapi::JS_IsArrayObject;
+use js::jsapi::JS_ValueToObject;
+use js::jsapi::JSObject;
+use js::jsapi::MutableHandleValue;
+use js::jsapi::RootedId;
+use js::jsapi::RootedObject;
+use js::jsapi::RootedValue;
+use js::jsapi::jsid;
+use js::jsval::{DoubleValue, UndefinedValue};
+use libc::c_char;
+use script::console::ConsoleValue;
+use script::native::NativeError;
+use script::stack::stack_lines;
+use std::ffi::CStr;
+use std::ptr;
+use std::slice;
+
+/// Only own, enumerable, string-keyed properties are inspected.
+const JSITER_OWNONLY: u32 = 0x8;
+
+/// The default number of nested objects to expand before eliding them.
+pub const DEFAULT_DEPTH: u32 = 2;
+
+/// Return a human-readable representation of `value`, along the lines of
+/// node's `util.inspect`. Objects nested more than `depth` levels deep are
+/// elided, and cyclic references are shown as `[Circular]`.
+pub unsafe fn inspect(cx: *mut JSContext,
+                      value: HandleValue,
+                      depth: u32)
+                      -> Result<String, NativeError> {
+    let mut inspector = Inspector {
+        cx: cx,
+        depth: depth,
+        ancestors: vec![],
+    };
+    inspector.value(value, 0)
+}
+
+/// Convert `value` to a serializable form. Objects nested more than `depth`
+/// levels deep, cyclic references and objects other than arrays and plain
+/// objects are represented by their inspected text.
+pub unsafe fn serialize(cx: *mut JSContext,
+                        value: HandleValue,
+                        depth: u32)
+                        -> Result<ConsoleValue, NativeError> {
+    let mut inspector = Inspector {
+        cx: cx,
+        depth: depth,
+        ancestors: vec![],
+    };
+    inspector.serialize(value, 0)
+}
+
+/// Return the text `inspect` would show for the value `value` was
+/// serialized from, without looking at the original value again.
+pub unsafe fn render(cx: *mut JSContext, value: &ConsoleValue) -> Result<String, NativeError> {
+    render_at(cx, value, 0)
+}
+
+unsafe fn render_at(cx: *mut JSContext,
+                    value: &ConsoleValue,
+                    level: u32)
+                    -> Result<String, NativeError> {
+    Ok(match *value {
+        ConsoleValue::Undefined => "undefined".to_owned(),
+        ConsoleValue::Null => "null".to_owned(),
+        ConsoleValue::Boolean(b) => b.to_string(),
+        ConsoleValue::Number(n) => try!(number_to_string(cx, n)),
+        ConsoleValue::String(ref s) if level == 0 => s.clone(),
+        ConsoleValue::String(ref s) => quote(s),
+        ConsoleValue::Other(ref s) => s.clone(),
+        ConsoleValue::Array(ref items) => {
+            let mut rendered = Vec::with_capacity(items.len());
+            for item in items {
+                rendered.push(try!(render_at(cx, item, level + 1)));
+            }
+            if rendered.is_empty() {
+                "[]".to_owned()
+            } else {
+                format!("[ {} ]", rendered.join(", "))
+            }
+        }
+        ConsoleValue::Object(ref properties) => {
+            let mut rendered = Vec::with_capacity(properties.len());
+            for &(ref name, ref value) in properties {
+                rendered.push(try!(render_property(cx, name, value, level)));
+            }
+            if rendered.is_empty() {
+                "{}".to_owned()
+            } else {
+                format!("{{ {} }}", rendered.join(", "))
+            }
+        }
+    })
+}
+
+/// Render the property `name` of an object at `level`.
+unsafe fn render_property(cx: *mut JSContext,
+                          name: &str,
+                          value: &ConsoleValue,
+                          level: u32)
+                          -> Result<String, NativeError> {
+    let value = try!(render_at(cx, value, level + 1));
+    Ok(if is_identifier(name) {
+        format!("{}: {}", name, value)
+    } else {
+        format!("{}: {}", quote(name), value)
+    })
+}
+
+/// Return a representation of `value` as it would appear nested inside an
+/// object, i.e., with strings quoted.
+pub unsafe fn inspect_nested(cx: *mut JSContext,
+                             value: HandleValue,
+                             depth: u32)
+                             -> Result<String, NativeError> {
+    let mut inspector = Inspector {
+        cx: cx,
+        depth: depth + 1,
+        ancestors: vec![],
+    };
+    inspector.value(value, 1)
+}
+
+/// Return the keys of the own, enumerable, string-keyed properties of
+/// `object`.
+pub unsafe fn own_keys(cx: *mut JSContext, object: HandleObject) -> Result<Vec<jsid>, NativeError> {
+    let ids = CreateAutoIdVector(cx);
+    if !GetPropertyKeys(cx, object, JSITER_OWNONLY, ids) {
+        DestroyAutoIdVector(ids);
+        return Err(NativeError::Exception);
+    }
+    let mut length = 0;
+    let keys = SliceAutoIdVector(ids, &mut length);
+    let keys = slice::from_raw_parts(keys, length).to_vec();
+    DestroyAutoIdVector(ids);
+    Ok(keys)
+}
+
+pub unsafe fn id_to_string(cx: *mut JSContext, id: HandleId) -> Result<String, NativeError> {
+    let mut value = RootedValue::new(cx, UndefinedValue());
+    if !JS_IdToValue(cx, id.get(), value.handle_mut()) {
+        return Err(NativeError::Exception);
+    }
+    to_string(cx, value.handle())
+}
+
+/// Convert a number to a string the way JavaScript does.
+pub unsafe fn number_to_string(cx: *mut JSContext, number: f64) -> Result<String, NativeError> {
+    let value = RootedValue::new(cx, DoubleValue(number));
+    to_string(cx, value.handle())
+}
+
+/// Describe a symbol the way `Symbol.prototype.toString()` does, e.g.,
+/// `Symbol(x)`; converting it to a string directly throws.
+unsafe fn symbol_to_string(cx: *mut JSContext, value: HandleValue) -> Result<String, NativeError> {
+    let mut wrapper = RootedObject::new(cx, ptr::null_mut());
+    if !JS_ValueToObject(cx, value, wrapper.handle_mut()) {
+        return Err(NativeError::Exception);
+    }
+    let mut string = RootedValue::new(cx, UndefinedValue());
+    try!(call_method(cx, wrapper.handle(), b"toString\0", string.handle_mut()));
+    to_string(cx, string.handle())
+}
+
+fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('\'');
+    for c in string.chars() {
+        match c {
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Convert `value` to a string, the way `String(value)` would.
+pub unsafe fn to_string(cx: *mut JSContext, value: HandleValue) -> Result<String, NativeError> {
+    String::from_jsval(cx, value, ()).map_err(|()| NativeError::Exception)
+}
+
+unsafe fn get_property(cx: *mut JSContext,
+                       object: HandleObject,
+                       name: &'static [u8],
+                       rval: MutableHandleValue)
+                       -> Result<(), NativeError> {
+    if !JS_GetProperty(cx, object, name.as_ptr() as *const c_char, rval) {
+        return Err(NativeError::Exception);
+    }
+    Ok(())
+}
+
+unsafe fn call_method(cx: *mut JSContext,
+                      object: HandleObject,
+                      name: &'static [u8],
+                      rval: MutableHandleValue)
+                      -> Result<(), NativeError> {
+    if !JS_CallFunctionName(cx,
+                            object,
+                            name.as_ptr() as *const c_char,
+                            &HandleValueArray::new(),
+                            rval) {
+        return Err(NativeError::Exception);
+    }
+    Ok(())
+}
+
+/// Fail with a `TypeError` unless `value`, described as `what`, is an
+/// object.
+fn expect_object(value: HandleValue, what: &str) -> Result<(), NativeError> {
+    if !value.is_object() {
+        return Err(NativeError::Type(format!("{} is not an object", what)));
+    }
+    Ok(())
+}
+
+struct Inspector {
+    cx: *mut JSContext,
+    depth: u32,
+    /// The objects currently being inspected, to detect cycles.
+    ancestors: Vec<*mut JSObject>,
+}
+
+impl Inspector {
+    unsafe fn value(&mut self, value: HandleValue, level: u32) -> Result<String, NativeError> {
+        let serialized = try!(self.serialize(value, level));
+        render_at(self.cx, &serialized, level)
+    }
+
+    unsafe fn serialize(&mut self,
+                        value: HandleValue,
+                        level: u32)
+                        -> Result<ConsoleValue, NativeError> {
+        if value.is_undefined() {
+            return Ok(ConsoleValue::Undefined);
+        }
+        if value.is_null() {
+            return Ok(ConsoleValue::Null);
+        }
+        if value.is_boolean() {
+            return Ok(ConsoleValue::Boolean(value.to_boolean()));
+        }
+        if value.is_number() {
+            return Ok(ConsoleValue::Number(value.to_number()));
+        }
+        if value.is_string() {
+            return Ok(ConsoleValue::String(try!(to_string(self.cx, value))));
+        }
+        if value.is_symbol() {
+            return Ok(ConsoleValue::Other(try!(symbol_to_string(self.cx, value))));
+        }
+        if !value.is_object() {
+            return Ok(ConsoleValue::Other(try!(to_string(self.cx, value))));
+        }
+
+        let object = RootedObject::new(self.cx, value.to_object());
+        if self.ancestors.contains(&object.ptr) {
+            return Ok(ConsoleValue::Other("[Circular]".to_owned()));
+        }
+        self.ancestors.push(object.ptr);
+        let result = self.object(object.handle(), value, level);
+        self.ancestors.pop();
+        result
+    }
+
+    unsafe fn object(&mut self,
+                     object: HandleObject,
+                     value: HandleValue,
+                     level: u32)
+                     -> Result<ConsoleValue, NativeError> {
+        let class = CStr::from_ptr((*JS_GetClass(object.get())).name).to_string_lossy().into_owned();
+        match &*class {
+            "Function" => return Ok(ConsoleValue::Other(try!(self.function(object)))),
+            "Error" => return Ok(ConsoleValue::Other(try!(self.error(object)))),
+            "Date" | "RegExp" | "String" | "Number" | "Boolean" => {
+                return Ok(ConsoleValue::Other(try!(to_string(self.cx, value))));
+            }
+            _ => (),
+        }
+
+        let is_array = JS_IsArrayObject(self.cx, object);
+        if level > self.depth {
+            let elided = if is_array { "[Array]" } else { "[Object]" };
+            return Ok(ConsoleValue::Other(elided.to_owned()));
+        }
+
+        if is_array {
+            return Ok(ConsoleValue::Array(try!(self.array(object, level))));
+        }
+
+        let mut entries = match &*class {
+            "Map" => try!(self.map(object, level)),
+            "Set" => try!(self.set(object, level)),
+            _ => return Ok(ConsoleValue::Object(try!(self.properties(object, level)))),
+        };
+        for (name, value) in try!(self.properties(object, level)) {
+            entries.push(try!(render_property(self.cx, &name, &value, level)));
+        }
+        Ok(ConsoleValue::Other(if entries.is_empty() {
+            format!("{} {{}}", class)
+        } else {
+            format!("{} {{ {} }}", class, entries.join(", "))
+        }))
+    }
+
+    unsafe fn function(&mut self, object: HandleObject) -> Result<String, NativeError> {
+        let mut name = RootedValue::new(self.cx, UndefinedValue());
+        try!(get_property(self.cx, object, b"name\0", name.handle_mut()));
+        let name = if name.ptr.is_string() {
+            try!(to_string(self.cx, name.handle()))
+        } else {
+            String::new()
+        };
+        Ok(if name.is_empty() {
+            "[Function]".to_owned()
+        } else {
+            format!("[Function: {}]", name)
+        })
+    }
+
+    unsafe fn error(&mut self, object: HandleObject) -> Result<String, NativeError> {
+        let mut name = RootedValue::new(self.cx, UndefinedValue());
+        let mut message = RootedValue::new(self.cx, UndefinedValue());
+        let mut stack = RootedValue::new(self.cx, UndefinedValue());
+        try!(get_property(self.cx, object, b"name\0", name.handle_mut()));
+        try!(get_property(self.cx, object, b"message\0", message.handle_mut()));
+        try!(get_property(self.cx, object, b"stack\0", stack.handle_mut()));
+        let mut result = format!("{}: {}",
+                                 try!(to_string(self.cx, name.handle())),
+                                 try!(to_string(self.cx, message.handle())));
+        if stack.ptr.is_string() {
+            let stack = try!(to_string(self.cx, stack.handle()));
+            for line in stack_lines(&stack) {
+                result.push('\n');
+                result.push_str(&line);
+            }
+        }
+        Ok(result)
+    }
+
+    unsafe fn array(&mut self,
+                    object: HandleObject,
+                    level: u32)
+                    -> Result<Vec<ConsoleValue>, NativeError> {
+        let mut length = 0;
+        if !JS_GetArrayLength(self.cx, object, &mut length) {
+            return Err(NativeError::Exception);
+        }
+        let mut items = Vec::with_capacity(length as usize);
+        let mut item = RootedValue::new(self.cx, UndefinedValue());
+        for index in 0..length {
+            if !JS_GetElement(self.cx, object, index, item.handle_mut()) {
+                return Err(NativeError::Exception);
+            }
+            items.push(try!(self.serialize(item.handle(), level + 1)));
+        }
+        Ok(items)
+    }
+
+    /// Call `next()` on the iterator returned by `object[method]()` until it
+    /// is exhausted, passing each value to `f`.
+    unsafe fn iterate<F>(&mut self,
+                         object: HandleObject,
+                         method: &'static [u8],
+                         mut f: F)
+                         -> Result<(), NativeError>
+        where F: FnMut(&mut Self, HandleValue) -> Result<(), NativeError>
+    {
+        let mut value = RootedValue::new(self.cx, UndefinedValue());
+        try!(call_method(self.cx, object, method, value.handle_mut()));
+        try!(expect_object(value.handle(), "iterator"));
+        let iterator = RootedObject::new(self.cx, value.ptr.to_object());
+        let mut result = RootedObject::new(self.cx, ptr::null_mut());
+        loop {
+            try!(call_method(self.cx, iterator.handle(), b"next\0", value.handle_mut()));
+            try!(expect_object(value.handle(), "iterator result"));
+            result.ptr = value.ptr.to_object();
+            try!(get_property(self.cx, result.handle(), b"done\0", value.handle_mut()));
+            if value.ptr.is_boolean() && value.ptr.to_boolean() {
+                return Ok(());
+            }
+            try!(get_property(self.cx, result.handle(), b"value\0", value.handle_mut()));
+            try!(f(self, value.handle()));
+        }
+    }
+
+    unsafe fn map(&mut self, object: HandleObject, level: u32) -> Result<Vec<String>, NativeError> {
+        let mut entries = vec![];
+        try!(self.iterate(object, b"entries\0", |this, entry| {
+            try!(expect_object(entry, "Map entry"));
+            let entry = RootedObject::new(this.cx, entry.to_object());
+            let mut key = RootedValue::new(this.cx, UndefinedValue());
+            let mut value = RootedValue::new(this.cx, UndefinedValue());
+            if !JS_GetElement(this.cx, entry.handle(), 0, key.handle_mut()) ||
+               !JS_GetElement(this.cx, entry.handle(), 1, value.handle_mut()) {
+                return Err(NativeError::Exception);
+            }
+            let key = try!(this.value(key.handle(), level + 1));
+            let value = try!(this.value(value.handle(), level + 1));
+            entries.push(format!("{} => {}", key, value));
+            Ok(())
+        }));
+        Ok(entries)
+    }
+
+    unsafe fn set(&mut self, object: HandleObject, level: u32) -> Result<Vec<String>, NativeError> {
+        let mut entries = vec![];
+        try!(self.iterate(object, b"values\0", |this, value| {
+            entries.push(try!(this.value(value, level + 1)));
+            Ok(())
+        }));
+        Ok(entries)
+    }
+
+    unsafe fn properties(&mut self,
+                         object: HandleObject,
+                         level: u32)
+                         -> Result<Vec<(String, ConsoleValue)>, NativeError> {
+        let keys = try!(own_keys(self.cx, object));
+        let mut properties = Vec::with_capacity(keys.len());
+        let mut value = RootedValue::new(self.cx, UndefinedValue());
+        for key in keys {
+            let key = RootedId::new(self.cx, key);
+            if !JS_GetPropertyById(self.cx, object, key.handle(), value.handle_mut()) {
+                return Err(NativeError::Exception);
+            }
+            let name = try!(id_to_string(self.cx, key.handle()));
+            properties.push((name, try!(self.serialize(value.handle(), level + 1))));
+        }
+        Ok(properties)
+    }
+}
@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use rustc_serialize::json::{Json, ToJson};
+use script::console::{ConsoleMessage, ConsoleMessageHandler, ConsoleValue};
+use script::stack::StackFrame;
+use script::table::Table;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// A console handler that writes each message as a JSON object on a line of
+/// its own.
+pub struct JsonHandler(RefCell<Box<Write>>);
+
+impl JsonHandler {
+    pub fn new(output: Box<Write>) -> JsonHandler {
+        JsonHandler(RefCell::new(output))
+    }
+
+    fn write(&self, record: Json) {
+        let mut output = self.0.borrow_mut();
+        let _ = writeln!(output, "{}", record);
+        let _ = output.flush();
+    }
+}
+
+impl ConsoleMessageHandler for JsonHandler {
+    fn log(&self, message: ConsoleMessage) {
+        self.write(message.to_json());
+    }
+
+    fn records_call_site(&self) -> bool {
+        true
+    }
+
+    fn table(&self, mut message: ConsoleMessage, table: Table) {
+        message.text = table.render();
+        let mut record = message.to_json();
+        if let Json::Object(ref mut object) = record {
+            object.insert("table".to_owned(), table.to_json());
+        }
+        self.write(record);
+    }
+}
+
+impl ToJson for ConsoleValue {
+    fn to_json(&self) -> Json {
+        match *self {
+            ConsoleValue::Undefined | ConsoleValue::Null => Json::Null,
+            ConsoleValue::Boolean(b) => Json::Boolean(b),
+            ConsoleValue::Number(n) => Json::F64(n),
+            ConsoleValue::String(ref s) | ConsoleValue::Other(ref s) => Json::String(s.clone()),
+            ConsoleValue::Array(ref items) => Json::Array(items.iter().map(|item| item.to_json()).collect()),
+            ConsoleValue::Object(ref properties) => {
+                Json::Object(properties.iter()
+                                       .map(|&(ref key, ref value)| (key.clone(), value.to_json()))
+                                       .collect())
+            }
+        }
+    }
+}
+
+impl ToJson for StackFrame {
+    fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        object.insert("function".to_owned(), self.function.to_json());
+        object.insert("filename".to_owned(), self.filename.to_json());
+        object.insert("line".to_owned(), self.line.to_json());
+        object.insert("column".to_owned(), self.column.to_json());
+        Json::Object(object)
+    }
+}
+
+impl ToJson for Table {
+    fn to_json(&self) -> Json {
+        let rows = self.rows.iter().map(|&(ref index, ref cells)| {
+            let mut row = BTreeMap::new();
+            row.insert("index".to_owned(), index.to_json());
+            row.insert("cells".to_owned(), cells.to_json());
+            Json::Object(row)
+        });
+        let mut object = BTreeMap::new();
+        object.insert("columns".to_owned(), self.columns.to_json());
+        object.insert("rows".to_owned(), Json::Array(rows.collect()));
+        Json::Object(object)
+    }
+}
+
+impl ToJson for ConsoleMessage {
+    fn to_json(&self) -> Json {
+        // Milliseconds since the Unix epoch, like `Date.now()`.
+        let timestamp = self.timestamp.sec as f64 * 1000. + self.timestamp.nsec as f64 / 1_000_000.;
+        let mut object = BTreeMap::new();
+        object.insert("level".to_owned(), self.level.name().to_json());
+        object.insert("message".to_owned(), self.text.to_json());
+        object.insert("arguments".to_owned(), self.arguments.to_json());
+        object.insert("timestamp".to_owned(), timestamp.to_json());
+        object.insert("filename".to_owned(), self.filename.to_json());
+        object.insert("line".to_owned(), self.line.to_json());
+        object.insert("column".to_owned(), self.column.to_json());
+        object.insert("groupDepth".to_owned(), self.group_depth.to_json());
+        if let Some(ref stack) = self.stack {
+            object.insert("stack".to_owned(), stack.to_json());
+        }
+        Json::Object(object)
+    }
+}
+
+#[test]
+fn message_json() {
+    use script::console::{LogLevel, TextSpan};
+    use std::f64;
+    use time::Timespec;
+
+    let message = ConsoleMessage {
+        level: LogLevel::Warn,
+        text: "careful".to_owned(),
+        spans: vec![TextSpan::plain("careful".to_owned())],
+        arguments: vec![ConsoleValue::Number(f64::NAN),
+                        ConsoleValue::Undefined,
+                        ConsoleValue::String("a".to_owned())],
+        timestamp: Timespec::new(1, 500_000_000),
+        filename: Some("test.js".to_owned()),
+        line: 3,
+        column: 7,
+        group_depth: 1,
+        stack: Some(vec![StackFrame {
+                             function: Some("f".to_owned()),
+                             filename: "test.js".to_owned(),
+                             line: 3,
+                             column: 7,
+                         }]),
+    };
+    let record = message.to_json();
+    assert_eq!(record["level"].as_string(), Some("warn"));
+    assert_eq!(record["message"].as_string(), Some("careful"));
+    // JSON has no NaN or undefined.
+    assert_eq!(record["arguments"].to_string(), r#"[null,null,"a"]"#);
+    assert_eq!(record["timestamp"].as_f64(), Some(1500.));
+    assert_eq!(record["filename"].as_string(), Some("test.js"));
+    assert_eq!(record["line"].as_u64(), Some(3));
+    assert_eq!(record["column"].as_u64(), Some(7));
+    assert_eq!(record["groupDepth"].as_u64(), Some(1));
+    assert_eq!(record["stack"].to_string(),
+               r#"[{"column":7,"filename":"test.js","function":"f","line":3}]"#);
+}
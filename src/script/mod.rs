@@ -3,30 +3,219 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 mod console;
+mod exception;
 mod global;
+mod inspect;
+mod json;
+mod native;
 mod reflect;
+mod source;
+mod stack;
+mod table;
+mod terminal;
+mod watchdog;
 
+pub use self::exception::Exception;
+pub use self::console::{ConsoleEntry, ConsoleMessage, ConsoleMessageHandler, ConsoleValue};
+pub use self::console::{LogLevel, StoringHandler, TextSpan};
+pub use self::json::JsonHandler;
+pub use self::source::Source;
+pub use self::terminal::{ColorChoice, TerminalHandler};
+pub use self::watchdog::Watchdog;
+
+use encoding::EncodingRef;
 use error::Error;
-use js::jsapi::{JS_Init, JSAutoRequest, Rooted};
-use js::rust::Runtime;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use js::jsapi::{Compile2, ContextOptionsRef, HandleObject, JS_BufferIsCompilableUnit};
+use js::jsapi::{JS_ExecuteScript, JS_GetRuntime, JS_Init};
+use js::jsapi::{JSAutoCompartment, JSAutoRequest, JSContext, MutableHandleValue, Rooted};
+use js::jsapi::{RootedScript, RootedValue};
+use js::jsval::UndefinedValue;
+use js::rust::{CompileOptionsWrapper, Runtime};
+use libc::{c_char, size_t};
+use script::exception::take_pending_exception;
+use script::inspect::{DEFAULT_DEPTH, inspect};
+use std::cell::Cell;
+use std::ffi::CString;
 use std::ptr;
+use std::rc::Rc;
 use std::sync::{Once, ONCE_INIT};
+use std::time::Duration;
 
 static INIT: Once = ONCE_INIT;
 
-fn load_script(path: &Path) -> Result<String, Error> {
-    let mut file = try!(File::open(path));
-    let mut buffer = vec![];
-    try!(file.read_to_end(&mut buffer));
-    let script = try!(String::from_utf8(buffer));
-    Ok(script)
+/// The error for the exception pending on `cx`, wrapped by `wrap`, or
+/// `Error::Error` if the failure did not leave an exception behind. If the
+/// exception was thrown from `script`, the offending line is attached to it.
+unsafe fn pending_error<F>(cx: *mut JSContext, script: &[u16], filename: &str, wrap: F) -> Error
+    where F: FnOnce(Exception) -> Error
+{
+    match take_pending_exception(cx) {
+        Some(mut exception) => {
+            if exception.filename == filename && exception.line > 0 {
+                exception.source_line = script.split(|&unit| unit == '\n' as u16)
+                                              .nth(exception.line as usize - 1)
+                                              .map(|line| {
+                                                  let line = String::from_utf16_lossy(line);
+                                                  line.trim_right_matches('\r').to_owned()
+                                              });
+            }
+            wrap(exception)
+        }
+        None => Error::Error,
+    }
+}
+
+/// Compile `script` and run it in `global`, storing its completion value in
+/// `rval`. Compilation failures are reported as `Error::Syntax` and uncaught
+/// exceptions as `Error::Runtime`.
+unsafe fn evaluate(cx: *mut JSContext,
+                   global: HandleObject,
+                   script: &[u16],
+                   filename: &str,
+                   line: u32,
+                   rval: MutableHandleValue)
+                   -> Result<(), Error> {
+    let _ac = JSAutoCompartment::new(cx, global.get());
+    let filename_cstr = CString::new(filename.replace("\0", "")).unwrap();
+    let options = CompileOptionsWrapper::new(cx, filename_cstr.as_ptr(), line);
+
+    let mut compiled = RootedScript::new(cx, ptr::null_mut());
+    if !Compile2(cx,
+                 options.ptr,
+                 script.as_ptr(),
+                 script.len() as size_t,
+                 compiled.handle_mut()) {
+        return Err(pending_error(cx, script, filename, Error::Syntax));
+    }
+    if !JS_ExecuteScript(cx, compiled.handle(), rval) {
+        return Err(runtime_error(cx, global, script, filename));
+    }
+    Ok(())
+}
+
+/// The error for JavaScript run in `global` that failed at runtime: an
+/// uncaught exception, or the script being stopped on purpose.
+unsafe fn runtime_error(cx: *mut JSContext,
+                        global: HandleObject,
+                        script: &[u16],
+                        filename: &str)
+                        -> Error {
+    // Without an exception, the script was stopped on purpose.
+    if watchdog::timed_out(cx) {
+        return Error::Timeout;
+    }
+    if let Some(code) = global::requested_exit(global.get()) {
+        return Error::Exit(code);
+    }
+    pending_error(cx, script, filename, Error::Runtime)
 }
 
-pub fn run_script(path: &Path) -> Result<(), Error> {
-    let script = try!(load_script(path));
+/// A runtime with a global object, in which scripts can be evaluated one
+/// after another.
+pub struct Context {
+    cx: *mut JSContext,
+    global: HandleObject,
+    /// The encoding of scripts without a byte order mark, if not detected.
+    encoding: Cell<Option<EncodingRef>>,
+}
+
+impl Context {
+    /// Decode the scripts loaded from now on as `encoding`, unless they start
+    /// with a byte order mark.
+    pub fn set_encoding(&self, encoding: EncodingRef) {
+        self.encoding.set(Some(encoding));
+    }
+
+    /// Load the script from `source` and run it in the global.
+    pub fn run(&self, source: &Source) -> Result<(), Error> {
+        let script = try!(source.load(self.encoding.get()));
+        let mut rval = RootedValue::new(self.cx, UndefinedValue());
+        unsafe {
+            evaluate(self.cx,
+                     self.global,
+                     &script,
+                     &source.filename(),
+                     1,
+                     rval.handle_mut())
+        }
+    }
+
+    /// Run the scripts from `sources` in the global, one after the other,
+    /// stopping at the first that fails or exits.
+    pub fn run_scripts(&self, sources: &[Source]) -> Result<(), Error> {
+        for source in sources {
+            try!(self.run(source).map_err(|error| {
+                match error {
+                    Error::Exit(code) => Error::Exit(code),
+                    error => Error::InScript(source.filename(), Box::new(error)),
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    /// The exit code the scripts set as `process.exitCode`, or 0.
+    pub fn exit_code(&self) -> Result<i32, Error> {
+        unsafe {
+            let _ac = JSAutoCompartment::new(self.cx, self.global.get());
+            global::exit_code(self.cx, self.global)
+                .map_err(|_| pending_error(self.cx, &[], "", Error::Runtime))
+        }
+    }
+
+    /// Stop the scripts run in the global once `timeout` has passed; they
+    /// fail with `Error::Timeout`. The limit lasts until the watchdog is
+    /// dropped.
+    pub fn watchdog(&self, timeout: Duration) -> Watchdog {
+        unsafe { Watchdog::new(JS_GetRuntime(self.cx), timeout) }
+    }
+
+    /// Run `script` in the global.
+    pub fn evaluate(&self, script: &str, filename: &str, line: u32) -> Result<(), Error> {
+        let script = script.utf16_units().collect::<Vec<u16>>();
+        let mut rval = RootedValue::new(self.cx, UndefinedValue());
+        unsafe { evaluate(self.cx, self.global, &script, filename, line, rval.handle_mut()) }
+    }
+
+    /// Run `script` in the global, and describe its completion value the way
+    /// `console.log()` would.
+    pub fn evaluate_and_inspect(&self,
+                                script: &str,
+                                filename: &str,
+                                line: u32)
+                                -> Result<String, Error> {
+        let script = script.utf16_units().collect::<Vec<u16>>();
+        let mut rval = RootedValue::new(self.cx, UndefinedValue());
+        unsafe {
+            try!(evaluate(self.cx, self.global, &script, filename, line, rval.handle_mut()));
+            let _ac = JSAutoCompartment::new(self.cx, self.global.get());
+            inspect(self.cx, rval.handle(), DEFAULT_DEPTH)
+                .map_err(|_| runtime_error(self.cx, self.global, &script, filename))
+        }
+    }
+
+    /// Whether `script` could be compiled, or whether more input is needed
+    /// to finish it. A syntax error counts as compilable, so it is reported.
+    pub fn is_compilable_unit(&self, script: &str) -> bool {
+        unsafe {
+            let _ac = JSAutoCompartment::new(self.cx, self.global.get());
+            JS_BufferIsCompilableUnit(self.cx,
+                                      self.global,
+                                      script.as_ptr() as *const c_char,
+                                      script.len() as size_t)
+        }
+    }
+}
+
+/// Create a runtime with a new global, logging console messages to
+/// `console_handler` and exposing `script_args` as `scriptArgs`, and pass
+/// it to `f`.
+pub fn with_context<F, T>(console_handler: Rc<ConsoleMessageHandler>,
+                          script_args: Vec<String>,
+                          f: F)
+                          -> Result<T, Error>
+    where F: FnOnce(&Context) -> Result<T, Error>
+{
     INIT.call_once(|| {
         unsafe {
             assert!(JS_Init());
@@ -35,55 +224,370 @@ pub fn run_script(path: &Path) -> Result<(), Error> {
 
     let runtime = Runtime::new();
     let _ar = JSAutoRequest::new(runtime.cx());
+    // Leave uncaught exceptions pending, so they can be reported as errors.
+    unsafe { (*ContextOptionsRef(runtime.cx())).set_dontReportUncaught_(true) };
     let mut global = Rooted::new(runtime.cx(), ptr::null_mut());
-    unsafe { global::create(runtime.cx(), global.handle_mut()) };
+    unsafe { global::create(runtime.cx(), console_handler, script_args, global.handle_mut()) };
     assert!(!global.ptr.is_null());
 
-    let path_string = path.to_string_lossy().into_owned();
-    try!(runtime.evaluate_script(global.handle(), script, path_string, 0));
-    Ok(())
+    f(&Context {
+        cx: runtime.cx(),
+        global: global.handle(),
+        encoding: Cell::new(None),
+    })
+}
+
+/// Run `f` in a new context without script arguments, storing its console
+/// messages instead of printing them.
+#[cfg(test)]
+fn in_context<F, T>(f: F) -> Result<T, Error>
+    where F: FnOnce(&Context) -> Result<T, Error>
+{
+    with_context(Rc::new(StoringHandler::new()), vec![], f)
+}
+
+/// The messages `handler` has stored, leaving out groups and tables.
+#[cfg(test)]
+fn messages(handler: &StoringHandler) -> Vec<ConsoleMessage> {
+    handler.get()
+           .iter()
+           .filter_map(|entry| {
+               match *entry {
+                   ConsoleEntry::Message(ref message) => Some(message.clone()),
+                   _ => None,
+               }
+           })
+           .collect()
 }
 
 #[test]
-fn missing_file() {
-    match load_script(Path::new("test-files/missing.js")) {
-        Err(Error::IO(_)) => (),
+fn running_tests() {
+    let source = Source::from_arg("test-files/success.js".as_ref());
+    match in_context(|context| context.run(&source)) {
+        Ok(()) => (),
         Err(error) => panic!("Unexpected error: {}", error),
-        Ok(_) => panic!("Unexpected ok"),
     }
 }
 
 #[test]
-fn non_utf8_file() {
-    match load_script(Path::new("test-files/non-utf8.js")) {
-        Err(Error::InvalidString(_)) => (),
+fn reference_error() {
+    let source = Source::from_arg("test-files/reference-error.js".as_ref());
+    match in_context(|context| context.run(&source)) {
+        Err(Error::Runtime(ref exception)) if exception.name == "ReferenceError" => {
+            assert_eq!(exception.line, 1);
+            assert_eq!(exception.source_line.as_ref().map(|line| &**line),
+                       Some("f();"));
+        }
         Err(error) => panic!("Unexpected error: {}", error),
-        Ok(_) => panic!("Unexpected ok"),
+        Ok(()) => panic!("Unexpected ok"),
     }
 }
 
 #[test]
-fn running_tests() {
-    match run_script(Path::new("test-files/success.js")) {
+fn shebang() {
+    let source = Source::from_arg("test-files/shebang.js".as_ref());
+    match in_context(|context| context.run(&source)) {
+        Err(Error::Runtime(ref exception)) if exception.name == "ReferenceError" => {
+            assert_eq!(exception.line, 2);
+            assert_eq!(exception.source_line.as_ref().map(|line| &**line), Some("f();"));
+        }
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(()) => panic!("Unexpected ok"),
+    }
+}
+
+#[test]
+fn syntax_error() {
+    let source = Source::from_arg("test-files/syntax-error.js".as_ref());
+    match in_context(|context| context.run(&source)) {
+        Err(Error::Syntax(ref exception)) => assert_eq!(exception.name, "SyntaxError"),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(()) => panic!("Unexpected ok"),
+    }
+}
+
+#[test]
+fn console_messages() {
+    let handler = Rc::new(StoringHandler::new());
+    let source = Source::from_arg("test-files/console.js".as_ref());
+    match with_context(handler.clone(), vec![], |context| context.run(&source)) {
         Ok(()) => (),
         Err(error) => panic!("Unexpected error: {}", error),
     }
+    let messages = messages(&handler)
+                       .into_iter()
+                       .map(|message| (message.level, message.text, message.line))
+                       .collect::<Vec<_>>();
+    assert_eq!(messages,
+               vec![(LogLevel::Log, "x = 1".to_owned(), 1),
+                    (LogLevel::Error, "oops".to_owned(), 2)]);
 }
 
 #[test]
-fn reference_error() {
-    match run_script(Path::new("test-files/reference-error.js")) {
-        Err(Error::Error) => (),
+fn format_directives() {
+    let handler = Rc::new(StoringHandler::new());
+    let script = ["console.log('%s|%d|%i|%f', 'a', 1.5, -2.5, 1.5);",
+                  "console.log('%o %O', 'x', { a: [1] });",
+                  "console.log('100%% %', 1);",
+                  "console.log('%s and %s', 'one');",
+                  "console.log('%cred%c plain', 'color: red', '');",
+                  "console.log('%s %f', Symbol('s'), 1e21);"]
+                     .join("\n");
+    match with_context(handler.clone(), vec![], |context| {
+        context.evaluate(&script, "format.js", 1)
+    }) {
+        Ok(()) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+    let messages = messages(&handler);
+    let texts = messages.iter().map(|message| &*message.text).collect::<Vec<_>>();
+    assert_eq!(texts,
+               ["a|1|-2|1.5",
+                "x { a: [ 1 ] }",
+                "100% % 1",
+                "one and %s",
+                "red plain",
+                "Symbol(s) 1e+21"]);
+    // The text is rendered from the same values that are stored.
+    let object = vec![("a".to_owned(), ConsoleValue::Array(vec![ConsoleValue::Number(1.0)]))];
+    assert_eq!(messages[1].arguments,
+               [ConsoleValue::String("%o %O".to_owned()),
+                ConsoleValue::String("x".to_owned()),
+                ConsoleValue::Object(object)]);
+    assert_eq!(messages[4].spans,
+               [TextSpan {
+                    text: "red".to_owned(),
+                    css: Some("color: red".to_owned()),
+                },
+                TextSpan {
+                    text: " plain".to_owned(),
+                    css: Some("".to_owned()),
+                }]);
+}
+
+#[test]
+fn assertions_and_traces() {
+    let handler = Rc::new(StoringHandler::new());
+    let script = ["function outer() { inner(); }",
+                  "function inner() {",
+                  "  console.assert(false);",
+                  "  console.assert(0, 'x is %d', 1);",
+                  "  console.assert(true, 'never');",
+                  "  console.trace('here');",
+                  "}",
+                  "outer();"]
+                     .join("\n");
+    match with_context(handler.clone(), vec![], |context| {
+        context.evaluate(&script, "stack.js", 1)
+    }) {
+        Ok(()) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+    let messages = messages(&handler)
+                       .into_iter()
+                       .map(|message| {
+                           let stack = message.stack.map(|stack| {
+                               stack.into_iter()
+                                    .map(|frame| (frame.function, frame.line))
+                                    .collect::<Vec<_>>()
+                           });
+                           (message.level, message.text, stack)
+                       })
+                       .collect::<Vec<_>>();
+    // The innermost frame first, down to the script itself.
+    let stack = |line: u32| {
+        Some(vec![(Some("inner".to_owned()), line), (Some("outer".to_owned()), 1), (None, 8)])
+    };
+    assert_eq!(messages,
+               vec![(LogLevel::Error, "Assertion failed".to_owned(), stack(3)),
+                    (LogLevel::Error, "Assertion failed: x is 1".to_owned(), stack(4)),
+                    (LogLevel::Trace, "here".to_owned(), stack(6))]);
+}
+
+#[test]
+fn console_state() {
+    let handler = Rc::new(StoringHandler::new());
+    let source = Source::from_arg("test-files/console-state.js".as_ref());
+    match with_context(handler.clone(), vec![], |context| context.run(&source)) {
+        Ok(()) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+    let entries = handler.get()
+                         .iter()
+                         .map(|entry| {
+                             match *entry {
+                                 ConsoleEntry::Message(ref message) => {
+                                     format!("{} {}", message.group_depth, message.text)
+                                 }
+                                 ConsoleEntry::GroupStart { ref message, .. } => {
+                                     format!("start {}", message.text)
+                                 }
+                                 ConsoleEntry::GroupEnd => "end".to_owned(),
+                                 ConsoleEntry::Table(..) => "table".to_owned(),
+                             }
+                         })
+                         .collect::<Vec<_>>();
+    assert_eq!(entries,
+               vec!["start outer", "1 inner", "end", "0 default: 1", "0 default: 2"]);
+}
+
+#[test]
+fn getters_run_once() {
+    let script = "var n = 0; console.log({ get x() { return ++n; } }); n";
+    match in_context(|context| context.evaluate_and_inspect(script, "getter.js", 1)) {
+        Ok(text) => assert_eq!(text, "1"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn persistent_context() {
+    let result = in_context(|context| {
+        assert!(!context.is_compilable_unit("function f() {"));
+        assert!(context.is_compilable_unit("function f() {\n  return 'x';\n}"));
+        try!(context.evaluate("function f() { return 'x'; }", "first.js", 1));
+        context.evaluate_and_inspect("[f(), 2]", "second.js", 1)
+    });
+    match result {
+        Ok(text) => assert_eq!(text, "[ 'x', 2 ]"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn inspect_values() {
+    let cases = [("var a = [1]; a.push(a); a", "[ 1, [Circular] ]"),
+                 ("({ a: { b: { c: { d: 1 } } } })", "{ a: { b: { c: [Object] } } }"),
+                 ("new Map([[1, 'a']])", "Map { 1 => 'a' }"),
+                 ("new Set([1, 2])", "Set { 1, 2 }"),
+                 ("(function f() {})", "[Function: f]"),
+                 ("(function () {})", "[Function]"),
+                 ("[Symbol('x'), Symbol()]", "[ Symbol(x), Symbol() ]"),
+                 ("[1e21, 1e-7, -1.5]", "[ 1e+21, 1e-7, -1.5 ]")];
+    for &(script, expected) in &cases {
+        match in_context(|context| context.evaluate_and_inspect(script, "inspect.js", 1)) {
+            Ok(text) => assert_eq!(text, expected),
+            Err(error) => panic!("Unexpected error: {}", error),
+        }
+    }
+
+    match in_context(|context| {
+        context.evaluate_and_inspect("new TypeError('bad')", "error.js", 1)
+    }) {
+        Ok(text) => assert!(text.starts_with("TypeError: bad\n    at @error.js:1:"), text),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+
+    match in_context(|context| {
+        context.evaluate_and_inspect("var m = new Map(); m.entries = function () { return 1; }; m",
+                                     "map.js",
+                                     1)
+    }) {
+        Err(Error::Runtime(ref exception)) => assert_eq!(exception.name, "TypeError"),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(text) => panic!("Unexpected ok: {}", text),
+    }
+}
+
+#[test]
+fn native_panic() {
+    // Without SERVO_PATH, launchBrowser panics, which must not abort.
+    ::std::env::remove_var("SERVO_PATH");
+    let script = "try { this.launchBrowser('about:blank'); false; } \
+                  catch (e) { e instanceof InternalError; }";
+    match in_context(|context| context.evaluate_and_inspect(script, "panic.js", 1)) {
+        Ok(text) => assert_eq!(text, "true"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn script_args() {
+    let args = vec!["a".to_owned(), "b c".to_owned()];
+    match with_context(Rc::new(StoringHandler::new()), args, |context| {
+        context.evaluate_and_inspect("[scriptArgs === scriptArgs, scriptArgs]", "args.js", 1)
+    }) {
+        Ok(text) => assert_eq!(text, "[ true, [ 'a', 'b c' ] ]"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn shared_global() {
+    // The second script calls the function the first one defines.
+    let sources = [Source::from_arg("test-files/success.js".as_ref()),
+                   Source::from_arg("test-files/reference-error.js".as_ref())];
+    match in_context(|context| context.run_scripts(&sources)) {
+        Ok(()) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn failing_script() {
+    let sources = [Source::from_arg("test-files/success.js".as_ref()),
+                   Source::from_arg("test-files/missing.js".as_ref())];
+    match in_context(|context| context.run_scripts(&sources)) {
+        Err(Error::InScript(ref filename, _)) => assert_eq!(filename, "test-files/missing.js"),
         Err(error) => panic!("Unexpected error: {}", error),
         Ok(()) => panic!("Unexpected ok"),
     }
 }
 
 #[test]
-fn syntax_error() {
-    match run_script(Path::new("test-files/syntax-error.js")) {
-        Err(Error::Error) => (),
+fn exit_codes() {
+    match in_context(|context| {
+        try!(context.evaluate("process.exitCode = 3;", "exit-code.js", 1));
+        context.exit_code()
+    }) {
+        Ok(code) => assert_eq!(code, 3),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+
+    match in_context(|context| {
+        context.evaluate("process.exit(5); throw new Error();", "exit.js", 1)
+    }) {
+        Err(Error::Exit(5)) => (),
         Err(error) => panic!("Unexpected error: {}", error),
         Ok(()) => panic!("Unexpected ok"),
     }
+
+    // Exiting cannot be caught, even when a native function is in between.
+    match in_context(|context| {
+        context.evaluate("try { console.log({ get x() { process.exit(3); } }); } catch (e) {}",
+                         "getter.js",
+                         1)
+    }) {
+        Err(Error::Exit(3)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(()) => panic!("Unexpected ok"),
+    }
+}
+
+#[test]
+fn timeout() {
+    match in_context(|context| {
+        let _watchdog = context.watchdog(Duration::from_millis(100));
+        context.evaluate("while (true) {}", "timeout.js", 1)
+    }) {
+        Err(Error::Timeout) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(()) => panic!("Unexpected ok"),
+    }
+
+    // Once the watchdog is gone, so is the limit.
+    match in_context(|context| {
+        {
+            let _watchdog = context.watchdog(Duration::from_millis(100));
+            match context.evaluate("while (true) {}", "timeout.js", 1) {
+                Err(Error::Timeout) => (),
+                Err(error) => panic!("Unexpected error: {}", error),
+                Ok(()) => panic!("Unexpected ok"),
+            }
+        }
+        context.evaluate("for (var i = 0; i < 1000; i++) {}", "after.js", 1)
+    }) {
+        Ok(()) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
 }
@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::error::throw_type_error;
+use js::jsapi::CurrentGlobalOrNull;
+use js::jsapi::JSContext;
+use js::jsapi::JSErrorFormatString;
+use js::jsapi::JSExnType;
+use js::jsapi::JS_IsExceptionPending;
+use js::jsapi::JS_ReportError;
+use js::jsapi::JS_ReportErrorNumber1;
+use libc::{c_char, c_void};
+use script::global;
+use script::watchdog;
+use std::any::Any;
+use std::ffi::CString;
+use std::panic::{self, AssertRecoverSafe};
+use std::ptr;
+
+/// The ways in which a native function called from JavaScript can fail.
+#[derive(PartialEq, Clone, Debug)]
+pub enum NativeError {
+    /// A JSAPI call failed and left an exception pending on the context.
+    Exception,
+    /// The Rust side of the function failed; thrown as an `Error`.
+    Failure(String),
+    /// An argument or `this` had the wrong type; thrown as a `TypeError`.
+    Type(String),
+    /// Stop running JavaScript altogether, without an exception.
+    Terminate,
+}
+
+impl NativeError {
+    /// Throw this error as a JavaScript exception on `cx`.
+    pub unsafe fn throw(self, cx: *mut JSContext) {
+        match self {
+            NativeError::Exception => {
+                // Without an exception, JavaScript may have been stopped on
+                // purpose, which must not become something it can catch.
+                if !JS_IsExceptionPending(cx) && !terminating(cx) {
+                    report_error(cx, "native function failed without an exception");
+                }
+            }
+            NativeError::Failure(message) => report_error(cx, &message),
+            NativeError::Type(message) => throw_type_error(cx, &message),
+            NativeError::Terminate => (),
+        }
+    }
+}
+
+/// Whether the JavaScript running on `cx` is being stopped by
+/// `process.exit()` or by the watchdog.
+unsafe fn terminating(cx: *mut JSContext) -> bool {
+    if watchdog::timed_out(cx) {
+        return true;
+    }
+    let global = CurrentGlobalOrNull(cx);
+    !global.is_null() && global::requested_exit(global).is_some()
+}
+
+unsafe fn report_error(cx: *mut JSContext, message: &str) {
+    let message = CString::new(message.replace("\0", "")).unwrap();
+    JS_ReportError(cx, b"%s\0".as_ptr() as *const c_char, message.as_ptr());
+}
+
+/// Run the Rust side of a native function and convert its result to the
+/// return value of the JSNative wrapper, throwing the error, if any, on `cx`.
+/// A panic in `native` is caught, rather than unwinding into SpiderMonkey,
+/// and thrown as an `InternalError`.
+pub unsafe fn call<F>(cx: *mut JSContext, native: F) -> bool
+    where F: FnOnce() -> Result<(), NativeError>
+{
+    // `native` is never looked at again after it panics.
+    let mut native = AssertRecoverSafe::new(Some(native));
+    let result = match panic::recover(move || native.take().unwrap()()) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = format!("a native function panicked: {}", panic_message(&payload));
+            throw_internal_error(cx, &message);
+            return false;
+        }
+    };
+    match result {
+        Ok(()) => true,
+        Err(error) => {
+            error.throw(cx);
+            false
+        }
+    }
+}
+
+/// The message passed to `panic!`, if it was a string.
+fn panic_message(payload: &Box<Any + Send>) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<Any>"
+    }
+}
+
+static mut INTERNAL_ERROR_FORMAT_STRING: JSErrorFormatString = JSErrorFormatString {
+    format: b"{0}\0" as *const u8 as *const c_char,
+    argCount: 1,
+    exnType: JSExnType::JSEXN_INTERNALERR as i16,
+};
+
+/// The error message callback for `throw_internal_error`. It is called from
+/// C, so it must not panic; unknown error numbers get no format string.
+unsafe extern "C" fn get_error_message(_user_ref: *mut c_void,
+                                       error_number: u32)
+                                       -> *const JSErrorFormatString {
+    if error_number == 0 {
+        &INTERNAL_ERROR_FORMAT_STRING
+    } else {
+        ptr::null()
+    }
+}
+
+/// Throw an `InternalError` with the given message on `cx`.
+unsafe fn throw_internal_error(cx: *mut JSContext, message: &str) {
+    let message = CString::new(message.replace("\0", "")).unwrap();
+    JS_ReportErrorNumber1(cx, Some(get_error_message), ptr::null_mut(), 0, message.as_ptr());
+}
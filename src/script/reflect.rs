@@ -2,7 +2,6 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use js::error::throw_type_error;
 use js::jsapi::HandleObject;
 use js::jsapi::HandleValue;
 use js::jsapi::JSClass;
@@ -24,6 +23,8 @@ use js::rust::define_methods;
 use js::rust::define_properties;
 use js::rust::GCMethods;
 use libc::c_void;
+use script::native::NativeError;
+use std::ffi::CStr;
 
 const DOM_OBJECT_SLOT: u32 = 0;
 
@@ -47,16 +48,15 @@ pub trait Reflectable: Sized {
         slot.to_private() as *const _
     }
 
-    unsafe fn from_value(cx: *mut JSContext, v: HandleValue) -> Result<*const Self, ()> {
+    unsafe fn from_value(v: HandleValue) -> Result<*const Self, NativeError> {
         if !v.is_object() {
-            throw_type_error(cx, "Value is not an object");
-            return Err(());
+            return Err(NativeError::Type("Value is not an object".to_owned()));
         }
 
         let object = v.to_object();
         if !Self::is(object) {
-            throw_type_error(cx, "Value is not an object");
-            return Err(());
+            let name = CStr::from_ptr(Self::class().name).to_string_lossy();
+            return Err(NativeError::Type(format!("Value is not a {} object", name)));
         }
 
         Ok(Self::from_reflector(object))
@@ -0,0 +1,167 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use encoding::{DecoderTrap, EncodingRef};
+use encoding::all::{UTF_8, WINDOWS_1252};
+use error::Error;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where a script is read from.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Source {
+    File(PathBuf),
+    Stdin,
+}
+
+impl Source {
+    /// The source named on the command line: a path, or `-` for stdin.
+    pub fn from_arg(arg: &OsStr) -> Source {
+        if arg == "-" {
+            Source::Stdin
+        } else {
+            Source::File(PathBuf::from(arg))
+        }
+    }
+
+    /// The filename that errors and stacks report for the script.
+    pub fn filename(&self) -> String {
+        match *self {
+            Source::File(ref path) => path.to_string_lossy().into_owned(),
+            Source::Stdin => "<stdin>".to_owned(),
+        }
+    }
+
+    /// Read and decode the script; see `decode_script`.
+    pub fn load(&self, encoding: Option<EncodingRef>) -> Result<Vec<u16>, Error> {
+        match *self {
+            Source::File(ref path) => load_script(path, encoding),
+            Source::Stdin => read_script(io::stdin(), encoding),
+        }
+    }
+}
+
+fn read_script<R: Read>(mut reader: R,
+                        encoding: Option<EncodingRef>)
+                        -> Result<Vec<u16>, Error> {
+    let mut buffer = vec![];
+    try!(reader.read_to_end(&mut buffer));
+    let mut script = try!(decode_script(&buffer, encoding));
+    strip_shebang(&mut script);
+    Ok(script)
+}
+
+/// Remove a `#!` line from the start of `script`, so it can be run as an
+/// executable. The line break stays, to keep the line numbers right.
+fn strip_shebang(script: &mut Vec<u16>) {
+    if script.starts_with(&['#' as u16, '!' as u16]) {
+        let end = script.iter().position(|&unit| unit == '\n' as u16).unwrap_or(script.len());
+        script.drain(..end);
+    }
+}
+
+fn load_script(path: &Path, encoding: Option<EncodingRef>) -> Result<Vec<u16>, Error> {
+    read_script(try!(File::open(path)), encoding)
+}
+
+/// Decode the bytes of a script to the UTF-16 that SpiderMonkey compiles.
+/// A byte order mark decides the encoding; failing that, `encoding` does.
+/// Without either, the script is UTF-8 if it can be, and windows-1252, like
+/// browsers assume for legacy content, otherwise. UTF-16 is taken as it is,
+/// even if it is not well-formed.
+pub fn decode_script(bytes: &[u8], encoding: Option<EncodingRef>) -> Result<Vec<u16>, Error> {
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        return decode(&bytes[3..], UTF_8);
+    }
+    if bytes.starts_with(b"\xFF\xFE") {
+        return Ok(utf16_units(&bytes[2..], Endianness::Little));
+    }
+    if bytes.starts_with(b"\xFE\xFF") {
+        return Ok(utf16_units(&bytes[2..], Endianness::Big));
+    }
+
+    match encoding {
+        Some(encoding) if encoding.name() == "utf-16le" => {
+            Ok(utf16_units(bytes, Endianness::Little))
+        }
+        Some(encoding) if encoding.name() == "utf-16be" => {
+            Ok(utf16_units(bytes, Endianness::Big))
+        }
+        Some(encoding) => decode(bytes, encoding),
+        None => decode(bytes, UTF_8).or_else(|_| decode(bytes, WINDOWS_1252)),
+    }
+}
+
+fn decode(bytes: &[u8], encoding: EncodingRef) -> Result<Vec<u16>, Error> {
+    match encoding.decode(bytes, DecoderTrap::Strict) {
+        Ok(text) => Ok(text.utf16_units().collect()),
+        Err(error) => Err(Error::InvalidString(format!("{} as {}", error, encoding.name()))),
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+/// Split `bytes` into UTF-16 code units. A trailing odd byte becomes U+FFFD.
+fn utf16_units(bytes: &[u8], endianness: Endianness) -> Vec<u16> {
+    bytes.chunks(2).map(|pair| {
+        if pair.len() < 2 {
+            return 0xFFFD;
+        }
+        let (high, low) = match endianness {
+            Endianness::Little => (pair[1], pair[0]),
+            Endianness::Big => (pair[0], pair[1]),
+        };
+        (high as u16) << 8 | low as u16
+    }).collect()
+}
+
+#[test]
+fn stdin() {
+    let source = Source::from_arg("-".as_ref());
+    assert_eq!(source, Source::Stdin);
+    assert_eq!(source.filename(), "<stdin>");
+    assert_eq!(Source::from_arg("./-".as_ref()).filename(), "./-");
+}
+
+#[test]
+fn read_from_memory() {
+    let input: &[u8] = b"#!/usr/bin/env runtime\nf();\n";
+    match read_script(input, None) {
+        Ok(script) => assert_eq!(String::from_utf16_lossy(&script), "\nf();\n"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn missing_file() {
+    match load_script(Path::new("test-files/missing.js"), None) {
+        Err(Error::IO(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unexpected ok"),
+    }
+}
+
+#[test]
+fn non_utf8_file() {
+    match load_script(Path::new("test-files/non-utf8.js"), None) {
+        Ok(script) => assert_eq!(String::from_utf16_lossy(&script), "\u{C0}\u{20AC}\n"),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn byte_order_marks() {
+    assert_eq!(decode_script(b"\xEF\xBB\xBF\xC3\xA9", Some(WINDOWS_1252 as EncodingRef)).ok(),
+               Some(vec![0xE9]));
+    // Lone surrogates survive.
+    assert_eq!(decode_script(b"\xFF\xFEa\x00\x00\xD8", None).ok(),
+               Some(vec![0x61, 0xD800]));
+    assert_eq!(decode_script(b"\xFE\xFF\x00a", None).ok(), Some(vec![0x61]));
+}
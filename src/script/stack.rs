@@ -0,0 +1,116 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::conversions::jsstring_to_str;
+use js::jsapi::CaptureCurrentStack;
+use js::jsapi::GetSavedFrameColumn;
+use js::jsapi::GetSavedFrameFunctionDisplayName;
+use js::jsapi::GetSavedFrameLine;
+use js::jsapi::GetSavedFrameParent;
+use js::jsapi::GetSavedFrameSource;
+use js::jsapi::JSContext;
+use js::jsapi::RootedObject;
+use js::jsapi::RootedString;
+use js::jsapi::SavedFrameResult;
+use script::native::NativeError;
+use std::fmt::{self, Display, Formatter};
+use std::ptr;
+
+/// The maximum number of frames captured for a single stack.
+const MAX_FRAMES: u32 = 64;
+
+/// A single frame of a captured JavaScript call stack.
+#[derive(PartialEq, Clone, Debug)]
+pub struct StackFrame {
+    /// The display name of the function, if it has one.
+    pub function: Option<String>,
+    pub filename: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.function {
+            Some(ref function) => {
+                write!(formatter, "{} ({}:{}:{})", function, self.filename, self.line, self.column)
+            }
+            None => write!(formatter, "{}:{}:{}", self.filename, self.line, self.column),
+        }
+    }
+}
+
+/// Format `frame`, a `StackFrame` or a line of the `stack` property of an
+/// error, as a line of a stack trace.
+pub fn stack_line<T: Display>(frame: T) -> String {
+    format!("    at {}", frame)
+}
+
+/// Format the `stack` property of an error as the lines of a stack trace.
+pub fn stack_lines(stack: &str) -> Vec<String> {
+    stack.lines().filter(|frame| !frame.is_empty()).map(stack_line).collect()
+}
+
+/// Reading a saved frame can fail without setting an exception, e.g., when
+/// it belongs to a compartment we cannot access.
+fn unreadable_frame() -> NativeError {
+    NativeError::Failure("could not read a frame of the call stack".to_owned())
+}
+
+/// Capture the JavaScript call stack of `cx`, innermost frame first.
+pub unsafe fn capture_stack(cx: *mut JSContext) -> Result<Vec<StackFrame>, NativeError> {
+    capture_frames(cx, MAX_FRAMES)
+}
+
+/// Return the innermost frame of the JavaScript call stack of `cx`, if any.
+pub unsafe fn current_frame(cx: *mut JSContext) -> Result<Option<StackFrame>, NativeError> {
+    let mut frames = try!(capture_frames(cx, 1));
+    Ok(frames.pop())
+}
+
+unsafe fn capture_frames(cx: *mut JSContext,
+                         max_frames: u32)
+                         -> Result<Vec<StackFrame>, NativeError> {
+    let mut frame = RootedObject::new(cx, ptr::null_mut());
+    if !CaptureCurrentStack(cx, frame.handle_mut(), max_frames) {
+        return Err(NativeError::Exception);
+    }
+
+    let mut frames = vec![];
+    let mut string = RootedString::new(cx, ptr::null_mut());
+    while !frame.ptr.is_null() {
+        let mut line = 0;
+        let mut column = 0;
+        if GetSavedFrameSource(cx, frame.handle(), string.handle_mut()) != SavedFrameResult::Ok ||
+           GetSavedFrameLine(cx, frame.handle(), &mut line) != SavedFrameResult::Ok ||
+           GetSavedFrameColumn(cx, frame.handle(), &mut column) != SavedFrameResult::Ok {
+            return Err(unreadable_frame());
+        }
+        let filename = jsstring_to_str(cx, string.ptr);
+
+        if GetSavedFrameFunctionDisplayName(cx, frame.handle(), string.handle_mut()) !=
+           SavedFrameResult::Ok {
+            return Err(unreadable_frame());
+        }
+        let function = if string.ptr.is_null() {
+            None
+        } else {
+            Some(jsstring_to_str(cx, string.ptr))
+        };
+
+        frames.push(StackFrame {
+            function: function,
+            filename: filename,
+            line: line,
+            column: column,
+        });
+
+        let mut parent = RootedObject::new(cx, ptr::null_mut());
+        if GetSavedFrameParent(cx, frame.handle(), parent.handle_mut()) != SavedFrameResult::Ok {
+            return Err(unreadable_frame());
+        }
+        frame.ptr = parent.ptr;
+    }
+    Ok(frames)
+}
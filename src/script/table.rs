@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::conversions::FromJSValConvertible;
+use js::jsapi::HandleValue;
+use js::jsapi::JSContext;
+use js::jsapi::JS_GetArrayLength;
+use js::jsapi::JS_GetElement;
+use js::jsapi::JS_GetPropertyById;
+use js::jsapi::JS_IsArrayObject;
+use js::jsapi::JS_ObjectIsFunction;
+use js::jsapi::RootedId;
+use js::jsapi::RootedObject;
+use js::jsapi::RootedValue;
+use js::jsval::UndefinedValue;
+use script::inspect::{id_to_string, inspect_nested, own_keys};
+use script::native::NativeError;
+use std::cmp;
+use std::iter;
+
+/// The name of the column holding the rows that are not objects.
+const VALUES_COLUMN: &'static str = "Values";
+
+/// The structured form of the data passed to `console.table()`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Table {
+    pub columns: Vec<String>,
+    /// The index of each row, and its cells, one per column.
+    pub rows: Vec<(String, Vec<Option<String>>)>,
+}
+
+impl Table {
+    /// Render the table as aligned text, with a header line and an index
+    /// column.
+    pub fn render(&self) -> String {
+        let mut header = vec!["(index)".to_owned()];
+        header.extend(self.columns.iter().cloned());
+        let lines = iter::once(header).chain(self.rows.iter().map(|&(ref index, ref cells)| {
+            let mut line = vec![index.clone()];
+            line.extend(cells.iter().map(|cell| cell.clone().unwrap_or_else(String::new)));
+            line
+        })).collect::<Vec<_>>();
+
+        let mut widths = vec![0; self.columns.len() + 1];
+        for line in &lines {
+            for (width, cell) in widths.iter_mut().zip(line) {
+                *width = cmp::max(*width, cell.chars().count());
+            }
+        }
+
+        let separator = widths.iter()
+                              .map(|&width| iter::repeat('-').take(width + 2).collect::<String>())
+                              .collect::<Vec<_>>()
+                              .join("+");
+        let mut rendered = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            let cells = line.iter().zip(&widths).map(|(cell, &width)| {
+                let padding = width - cell.chars().count();
+                format!(" {}{} ", cell, iter::repeat(' ').take(padding).collect::<String>())
+            });
+            rendered.push(format!("|{}|", cells.collect::<Vec<_>>().join("|")));
+            if i == 0 {
+                rendered.push(format!("|{}|", separator));
+            }
+        }
+        rendered.join("\n")
+    }
+}
+
+fn column_index(columns: &mut Vec<String>, name: &str) -> usize {
+    match columns.iter().position(|column| column == name) {
+        Some(index) => index,
+        None => {
+            columns.push(name.to_owned());
+            columns.len() - 1
+        }
+    }
+}
+
+/// Convert the `columns` argument of `console.table()` to a list of column
+/// names, or `None` if it is not an array.
+unsafe fn column_filter(cx: *mut JSContext,
+                        columns: HandleValue)
+                        -> Result<Option<Vec<String>>, NativeError> {
+    if !columns.is_object() {
+        return Ok(None);
+    }
+    let columns = RootedObject::new(cx, columns.to_object());
+    if !JS_IsArrayObject(cx, columns.handle()) {
+        return Ok(None);
+    }
+
+    let mut length = 0;
+    if !JS_GetArrayLength(cx, columns.handle(), &mut length) {
+        return Err(NativeError::Exception);
+    }
+    let mut names = Vec::with_capacity(length as usize);
+    let mut name = RootedValue::new(cx, UndefinedValue());
+    for index in 0..length {
+        if !JS_GetElement(cx, columns.handle(), index, name.handle_mut()) {
+            return Err(NativeError::Exception);
+        }
+        let name = try!(String::from_jsval(cx, name.handle(), ())
+                            .map_err(|()| NativeError::Exception));
+        names.push(name);
+    }
+    Ok(Some(names))
+}
+
+/// Build a table from `data`, with a row for each of its properties. If
+/// `filter` is an array, only the columns it names are shown.
+/// Returns `None` if `data` is not an object and cannot be tabulated.
+pub unsafe fn build_table(cx: *mut JSContext,
+                          data: HandleValue,
+                          filter: HandleValue)
+                          -> Result<Option<Table>, NativeError> {
+    if !data.is_object() {
+        return Ok(None);
+    }
+
+    let filter = try!(column_filter(cx, filter));
+
+    let data = RootedObject::new(cx, data.to_object());
+    let mut columns = filter.clone().unwrap_or_else(Vec::new);
+    let mut rows = vec![];
+    let mut row = RootedValue::new(cx, UndefinedValue());
+    let mut cell = RootedValue::new(cx, UndefinedValue());
+    for key in try!(own_keys(cx, data.handle())) {
+        let key = RootedId::new(cx, key);
+        let index = try!(id_to_string(cx, key.handle()));
+        if !JS_GetPropertyById(cx, data.handle(), key.handle(), row.handle_mut()) {
+            return Err(NativeError::Exception);
+        }
+
+        let mut cells = vec![];
+        let is_object = row.ptr.is_object() && !JS_ObjectIsFunction(cx, row.ptr.to_object());
+        if is_object {
+            let object = RootedObject::new(cx, row.ptr.to_object());
+            for key in try!(own_keys(cx, object.handle())) {
+                let key = RootedId::new(cx, key);
+                let name = try!(id_to_string(cx, key.handle()));
+                if filter.as_ref().map_or(false, |filter| !filter.contains(&name)) {
+                    continue;
+                }
+                if !JS_GetPropertyById(cx, object.handle(), key.handle(), cell.handle_mut()) {
+                    return Err(NativeError::Exception);
+                }
+                let column = column_index(&mut columns, &name);
+                cells.push((Some(column), try!(inspect_nested(cx, cell.handle(), 0))));
+            }
+        } else if filter.is_none() {
+            cells.push((None, try!(inspect_nested(cx, row.handle(), 0))));
+        }
+        rows.push((index, cells));
+    }
+
+    // The values column always comes last.
+    let has_values = rows.iter().any(|&(_, ref cells)| {
+        cells.iter().any(|&(column, _)| column.is_none())
+    });
+    if has_values {
+        columns.push(VALUES_COLUMN.to_owned());
+    }
+
+    let rows = rows.into_iter().map(|(index, cells)| {
+        let mut line = vec![None; columns.len()];
+        for (column, cell) in cells {
+            line[column.unwrap_or_else(|| columns.len() - 1)] = Some(cell);
+        }
+        (index, line)
+    }).collect();
+
+    Ok(Some(Table {
+        columns: columns,
+        rows: rows,
+    }))
+}
+
+#[test]
+fn render_table() {
+    let table = Table {
+        columns: vec!["name".to_owned(), "Values".to_owned()],
+        rows: vec![("0".to_owned(), vec![Some("'a'".to_owned()), None]),
+                   ("1".to_owned(), vec![None, Some("42".to_owned())])],
+    };
+    assert_eq!(table.render(),
+               "| (index) | name | Values |\n\
+                |---------+------+--------|\n\
+                | 0       | 'a'  |        |\n\
+                | 1       |      | 42     |");
+}
@@ -0,0 +1,158 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use libc;
+use script::console::{ConsoleMessage, ConsoleMessageHandler, LogLevel, TextSpan, layout};
+use std::io::{self, Write};
+
+const RESET: &'static str = "\x1b[0m";
+
+/// When to colour console output.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    fn use_color(self, fd: libc::c_int) -> bool {
+        match self {
+            ColorChoice::Auto => unsafe { libc::isatty(fd) != 0 },
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// A console handler that writes to stdout, or to stderr for diagnostics,
+/// colouring messages by level when the stream supports it.
+pub struct TerminalHandler {
+    stdout_color: bool,
+    stderr_color: bool,
+}
+
+impl TerminalHandler {
+    pub fn new(choice: ColorChoice) -> TerminalHandler {
+        TerminalHandler {
+            stdout_color: choice.use_color(libc::STDOUT_FILENO),
+            stderr_color: choice.use_color(libc::STDERR_FILENO),
+        }
+    }
+}
+
+impl ConsoleMessageHandler for TerminalHandler {
+    fn log(&self, message: ConsoleMessage) {
+        let diagnostic = message.level.is_diagnostic();
+        let color = if diagnostic { self.stderr_color } else { self.stdout_color };
+        let text = if color {
+            colorize(message.level, &message.spans)
+        } else {
+            message.text.clone()
+        };
+        let text = layout(&message, &text);
+        if diagnostic {
+            let _ = writeln!(&mut io::stderr(), "{}", text);
+        } else {
+            println!("{}", text);
+        }
+    }
+}
+
+/// The ANSI SGR parameters for messages of `level`.
+fn level_codes(level: LogLevel) -> Vec<&'static str> {
+    match level {
+        LogLevel::Error => vec!["31"],
+        LogLevel::Warn => vec!["33"],
+        LogLevel::Debug => vec!["2"],
+        LogLevel::Log | LogLevel::Info | LogLevel::Trace => vec![],
+    }
+}
+
+/// The ANSI SGR parameter for the foreground colour `name`; the background
+/// colour is ten more.
+fn color_code(name: &str) -> Option<u8> {
+    Some(match name {
+        "black" => 30,
+        "red" | "darkred" => 31,
+        "green" | "lime" => 32,
+        "yellow" | "orange" => 33,
+        "blue" | "navy" => 34,
+        "magenta" | "purple" | "fuchsia" => 35,
+        "cyan" | "aqua" | "teal" => 36,
+        "white" => 37,
+        "gray" | "grey" => 90,
+        _ => return None,
+    })
+}
+
+/// Map the basic declarations of `css` to ANSI SGR parameters; anything
+/// else is ignored.
+fn css_codes(css: &str) -> Vec<String> {
+    let mut codes = vec![];
+    for declaration in css.split(';') {
+        let mut parts = declaration.splitn(2, ':');
+        let property = parts.next().unwrap_or("").trim().to_lowercase();
+        let value = parts.next().unwrap_or("").trim().to_lowercase();
+        match (&*property, &*value) {
+            ("color", color) => {
+                if let Some(code) = color_code(color) {
+                    codes.push(code.to_string());
+                }
+            }
+            ("background", color) | ("background-color", color) => {
+                if let Some(code) = color_code(color) {
+                    codes.push((code + 10).to_string());
+                }
+            }
+            ("font-weight", "bold") => codes.push("1".to_owned()),
+            ("font-style", "italic") => codes.push("3".to_owned()),
+            ("text-decoration", "underline") => codes.push("4".to_owned()),
+            _ => (),
+        }
+    }
+    codes
+}
+
+/// Render `spans` with ANSI escape sequences for the colour of `level` and
+/// the CSS of each span.
+fn colorize(level: LogLevel, spans: &[TextSpan]) -> String {
+    let base = level_codes(level);
+    let mut result = String::new();
+    for span in spans {
+        let mut codes = base.iter().map(|&code| code.to_owned()).collect::<Vec<_>>();
+        if let Some(ref css) = span.css {
+            codes.extend(css_codes(css));
+        }
+        if codes.is_empty() {
+            result.push_str(&span.text);
+        } else {
+            result.push_str(&format!("\x1b[{}m{}{}", codes.join(";"), span.text, RESET));
+        }
+    }
+    result
+}
+
+#[test]
+fn colorize_css() {
+    let spans = vec![TextSpan::plain("plain ".to_owned()),
+                     TextSpan {
+                         text: "styled".to_owned(),
+                         css: Some("color: red; font-weight: bold; margin: 0".to_owned()),
+                     }];
+    assert_eq!(colorize(LogLevel::Log, &spans),
+               "plain \x1b[31;1mstyled\x1b[0m");
+    assert_eq!(colorize(LogLevel::Warn, &spans[..1]),
+               "\x1b[33mplain \x1b[0m");
+}
@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use js::jsapi::JSContext;
+use js::jsapi::JSRuntime;
+use js::jsapi::JS_GetRuntime;
+use js::jsapi::JS_GetRuntimePrivate;
+use js::jsapi::JS_RequestInterruptCallback;
+use js::jsapi::JS_SetInterruptCallback;
+use js::jsapi::JS_SetRuntimePrivate;
+use libc::c_void;
+use std::ptr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use time::precise_time_ns;
+
+/// Whether the script running on `cx` was terminated because the watchdog
+/// of its runtime ran out of time. Always false without a watchdog.
+pub unsafe fn timed_out(cx: *mut JSContext) -> bool {
+    // While a watchdog is alive, the private data of the runtime is its flag.
+    let timed_out = JS_GetRuntimePrivate(JS_GetRuntime(cx)) as *const AtomicBool;
+    !timed_out.is_null() && (*timed_out).load(Ordering::SeqCst)
+}
+
+unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
+    // Returning false terminates the script without an exception.
+    !timed_out(cx)
+}
+
+/// The runtime, for the watchdog thread to interrupt.
+struct RuntimePtr(*mut JSRuntime);
+
+// The watchdog thread only requests an interrupt, which is thread-safe, and
+// only while the runtime is alive.
+unsafe impl Send for RuntimePtr {}
+
+/// A thread that terminates the JavaScript running in a runtime once a
+/// timeout expires. It must be dropped before the runtime, and a runtime
+/// can only have one watchdog at a time.
+pub struct Watchdog {
+    rt: *mut JSRuntime,
+    /// Set once the timeout has expired.
+    timed_out: Arc<AtomicBool>,
+    /// Whether the watchdog has been dropped, and a way to wake its thread.
+    finished: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub unsafe fn new(rt: *mut JSRuntime, timeout: Duration) -> Watchdog {
+        let timed_out = Arc::new(AtomicBool::new(false));
+        JS_SetRuntimePrivate(rt, &*timed_out as *const AtomicBool as *mut c_void);
+        JS_SetInterruptCallback(rt, Some(interrupt_callback));
+
+        let thread_timed_out = timed_out.clone();
+
+        let finished = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_finished = finished.clone();
+        let runtime = RuntimePtr(rt);
+        let timeout_ns = timeout.as_secs() * 1_000_000_000 + timeout.subsec_nanos() as u64;
+        let deadline = precise_time_ns() + timeout_ns;
+        let thread = thread::spawn(move || {
+            let runtime = runtime;
+            let &(ref lock, ref condvar) = &*thread_finished;
+            let mut finished = lock.lock().unwrap();
+            // Waiting can end early, so check the clock every time.
+            loop {
+                if *finished {
+                    return;
+                }
+                let now = precise_time_ns();
+                if now >= deadline {
+                    break;
+                }
+                let remaining = deadline - now;
+                let wait = Duration::new(remaining / 1_000_000_000,
+                                         (remaining % 1_000_000_000) as u32);
+                finished = condvar.wait_timeout(finished, wait).unwrap().0;
+            }
+            // Still holding the lock, so the runtime cannot go away.
+            thread_timed_out.store(true, Ordering::SeqCst);
+            unsafe { JS_RequestInterruptCallback(runtime.0) };
+        });
+
+        Watchdog {
+            rt: rt,
+            timed_out: timed_out,
+            finished: finished,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        {
+            let &(ref lock, ref condvar) = &*self.finished;
+            *lock.lock().unwrap() = true;
+            condvar.notify_one();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        // An interrupt may still be pending; let it pass.
+        self.timed_out.store(false, Ordering::SeqCst);
+        unsafe {
+            JS_SetInterruptCallback(self.rt, None);
+            JS_SetRuntimePrivate(self.rt, ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,8 @@
+if (console !== console) {
+  throw new Error("console is not cached");
+}
+console.group("outer");
+console.log("inner");
+console.groupEnd();
+console.count();
+console.count();
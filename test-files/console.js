@@ -0,0 +1,2 @@
+console.log("x =", 1);
+console.error("oops");